@@ -0,0 +1,232 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+
+use crate::utils::{
+    aov::Aov,
+    image::exr,
+    scene_file::{self, SceneDescription},
+    tone_mapping::ToneMapper,
+};
+
+pub const USAGE: &str = "\
+usage: graphics-3d [--headless] [options]
+
+options:
+    --scene <path>         scene file to render (default scenes/default.toml, built in)
+    --headless             render a single frame to --output instead of opening a window
+    --width <px>           image width (headless only, default 800)
+    --height <px>          image height (headless only, default 600)
+    --samples <n>          samples per pixel, overrides the scene file
+    --max-depth <n>        maximum amount of bounces per ray, overrides the scene file
+    --tone-mapping <op>    clamp, reinhard or aces, overrides the scene file
+    --exposure <stops>     brightens or darkens the image, overrides the scene file
+    --denoise              filters the noise of low sample counts away, overrides the scene file
+    --output <path>        output image, .png, .ppm, .exr or .hdr (default render.png),
+                           exr and hdr keep the linear colors, without tone mapping
+    --exr-precision <p>    half or float channels (default half)
+    --exr-compression <c>  none or zip (default zip)
+    --aovs <list>          also writes these output variables next to --output, comma separated:
+                           albedo, normal, depth, material_id and object_id
+    --help                 print this message";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    pub scene: Option<PathBuf>,
+    pub headless: bool,
+    pub width: u32,
+    pub height: u32,
+    pub samples: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub tone_mapper: Option<ToneMapper>,
+    pub exposure: Option<f32>,
+    pub denoise: bool,
+    pub output: PathBuf,
+    pub exr: exr::Options,
+    pub aovs: Vec<Aov>,
+    pub help: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            scene: None,
+            headless: false,
+            width: 800,
+            height: 600,
+            samples: None,
+            max_depth: None,
+            tone_mapper: None,
+            exposure: None,
+            denoise: false,
+            output: PathBuf::from("render.png"),
+            exr: exr::Options::default(),
+            aovs: vec![],
+            help: false,
+        }
+    }
+}
+
+impl Args {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> anyhow::Result<Args> {
+        let mut parsed = Args::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .with_context(|| format!("missing value for `{arg}`"))
+            };
+
+            match arg.as_str() {
+                "--scene" => parsed.scene = Some(PathBuf::from(value()?)),
+                "--headless" => parsed.headless = true,
+                "--width" => parsed.width = parse_number(&arg, &value()?)?,
+                "--height" => parsed.height = parse_number(&arg, &value()?)?,
+                "--samples" => parsed.samples = Some(parse_number(&arg, &value()?)?),
+                "--max-depth" => parsed.max_depth = Some(parse_number(&arg, &value()?)?),
+                "--tone-mapping" => parsed.tone_mapper = Some(value()?.parse()?),
+                "--exposure" => parsed.exposure = Some(parse_number(&arg, &value()?)?),
+                "--denoise" => parsed.denoise = true,
+                "--output" => parsed.output = PathBuf::from(value()?),
+                "--exr-precision" => parsed.exr.precision = value()?.parse()?,
+                "--exr-compression" => parsed.exr.compression = value()?.parse()?,
+                "--aovs" => {
+                    parsed.aovs = value()?
+                        .split(',')
+                        .map(|aov| aov.trim().parse())
+                        .collect::<anyhow::Result<_>>()?
+                }
+                "--help" | "-h" => parsed.help = true,
+                _ => bail!("unknown argument `{arg}`\n\n{USAGE}"),
+            }
+        }
+
+        if parsed.width == 0 || parsed.height == 0 {
+            bail!("width and height must be greater than 0");
+        }
+        if parsed.samples == Some(0) {
+            bail!("samples must be greater than 0");
+        }
+
+        Ok(parsed)
+    }
+
+    /// Loads the scene file, applying the overrides given on the command line
+    pub fn load_scene(&self) -> anyhow::Result<SceneDescription> {
+        let mut scene = match &self.scene {
+            Some(path) => scene_file::load(path)?,
+            None => scene_file::default_scene(),
+        };
+
+        if let Some(samples) = self.samples {
+            scene.samples = samples;
+        }
+        if let Some(max_depth) = self.max_depth {
+            scene.max_depth = max_depth;
+        }
+        if let Some(tone_mapper) = self.tone_mapper {
+            scene.tone_mapping.tone_mapper = tone_mapper;
+        }
+        if let Some(exposure) = self.exposure {
+            scene.tone_mapping.exposure = exposure;
+        }
+        if self.denoise {
+            scene.denoise = true;
+        }
+
+        Ok(scene)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> anyhow::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    value
+        .parse()
+        .with_context(|| format!("invalid value `{value}` for `{flag}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::Args;
+    use crate::utils::{aov::Aov, image::exr, tone_mapping::ToneMapper};
+
+    fn parse(args: &[&str]) -> anyhow::Result<Args> {
+        Args::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_is_default() {
+        assert_eq!(parse(&[]).unwrap(), Args::default());
+    }
+
+    #[test]
+    fn headless_flags_are_parsed() {
+        let args = parse(&[
+            "--scene",
+            "scenes/room.toml",
+            "--headless",
+            "--width",
+            "320",
+            "--height",
+            "240",
+            "--samples",
+            "16",
+            "--max-depth",
+            "8",
+            "--tone-mapping",
+            "aces",
+            "--exposure",
+            "-1.5",
+            "--denoise",
+            "--output",
+            "out.exr",
+            "--exr-precision",
+            "float",
+            "--exr-compression",
+            "none",
+            "--aovs",
+            "albedo, depth",
+        ])
+        .unwrap();
+
+        assert_eq!(args.scene, Some(PathBuf::from("scenes/room.toml")));
+        assert!(args.headless);
+        assert_eq!(args.width, 320);
+        assert_eq!(args.height, 240);
+        assert_eq!(args.samples, Some(16));
+        assert_eq!(args.max_depth, Some(8));
+        assert_eq!(args.tone_mapper, Some(ToneMapper::Aces));
+        assert_eq!(args.exposure, Some(-1.5));
+        assert!(args.denoise);
+        assert_eq!(args.output, PathBuf::from("out.exr"));
+        assert_eq!(args.exr.precision, exr::Precision::Float);
+        assert_eq!(args.exr.compression, exr::Compression::None);
+        assert_eq!(args.aovs, [Aov::Albedo, Aov::Depth]);
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        assert!(parse(&["--width"]).is_err());
+        assert!(parse(&["--width", "abc"]).is_err());
+        assert!(parse(&["--height", "0"]).is_err());
+        assert!(parse(&["--unknown"]).is_err());
+        assert!(parse(&["--tone-mapping", "filmic"]).is_err());
+        assert!(parse(&["--exr-compression", "piz"]).is_err());
+        assert!(parse(&["--aovs", "albedo,color"]).is_err());
+    }
+
+    #[test]
+    fn overrides_are_applied_to_the_scene() {
+        let scene = parse(&["--samples", "3"]).unwrap().load_scene().unwrap();
+        assert_eq!(scene.samples, 3);
+        assert_eq!(scene.max_depth, 100);
+        assert!(!scene.denoise);
+
+        let scene = parse(&["--denoise"]).unwrap().load_scene().unwrap();
+        assert!(scene.denoise);
+    }
+}
@@ -0,0 +1,106 @@
+use glam::{Quat, Vec3};
+
+use crate::utils::camera::CameraBuilder;
+
+/// Radians the camera orbits for each pixel the mouse is dragged
+pub const ORBIT_SENSITIVITY: f32 = 0.005;
+/// Fraction of the distance to the target covered by a single key press
+const MOVE_STEP: f32 = 0.1;
+/// Fraction of the distance to the target zoomed by a single scroll line
+const ZOOM_STEP: f32 = 0.1;
+/// Keeps the camera from flipping over the poles while orbiting
+const MAX_PITCH: f32 = 1.5;
+
+/// Moves both the camera and its target, `direction` is in camera space:
+/// x to the right, y up and z forward
+pub fn translate(camera: &mut CameraBuilder, direction: Vec3) {
+    let view = camera.look_at - camera.look_from;
+    let forward = view.normalize();
+    let right = forward.cross(camera.vup).normalize();
+    let up = right.cross(forward);
+
+    let step = (view.length() * MOVE_STEP).max(0.01);
+    let offset = (direction.x * right + direction.y * up + direction.z * forward) * step;
+
+    camera.look_from += offset;
+    camera.look_at += offset;
+}
+
+/// Rotates the camera around its target, keeping the distance
+pub fn orbit(camera: &mut CameraBuilder, yaw: f32, pitch: f32) {
+    let offset = camera.look_from - camera.look_at;
+    let up = camera.vup.normalize();
+    let right = up.cross(offset).normalize();
+
+    let current_pitch = (offset.normalize().dot(up)).clamp(-1., 1.).asin();
+    let pitch = (current_pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH) - current_pitch;
+
+    let rotation = Quat::from_axis_angle(up, -yaw) * Quat::from_axis_angle(right, -pitch);
+
+    camera.look_from = camera.look_at + rotation * offset;
+}
+
+/// Moves the camera towards its target, positive `lines` get closer
+pub fn zoom(camera: &mut CameraBuilder, lines: f32) {
+    let offset = camera.look_from - camera.look_at;
+    let factor = (1. - ZOOM_STEP).powf(lines);
+
+    // Never reach the target, otherwise the view direction is lost
+    let distance = (offset.length() * factor).max(0.01);
+
+    camera.look_from = camera.look_at + offset.normalize() * distance;
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::{orbit, translate, zoom};
+    use crate::utils::camera::CameraBuilder;
+
+    fn camera() -> CameraBuilder {
+        CameraBuilder::default()
+            .look_from(Vec3::new(0., 0., 2.))
+            .look_at(Vec3::ZERO)
+    }
+
+    #[test]
+    fn translate_keeps_the_view_direction() {
+        let mut c = camera();
+        translate(&mut c, Vec3::new(1., 0., 1.));
+
+        assert!(c.look_from.abs_diff_eq(Vec3::new(0.2, 0., 1.8), 1e-6));
+        assert_eq!(
+            c.look_from - c.look_at,
+            camera().look_from - camera().look_at
+        );
+    }
+
+    #[test]
+    fn orbit_keeps_the_distance() {
+        let mut c = camera();
+        orbit(&mut c, std::f32::consts::FRAC_PI_2, 0.);
+
+        assert!((c.look_from.length() - 2.).abs() < 1e-5);
+        assert!(c.look_from.abs_diff_eq(Vec3::new(-2., 0., 0.), 1e-5));
+        assert_eq!(c.look_at, Vec3::ZERO);
+
+        // Pitch is clamped before reaching the pole
+        orbit(&mut c, 0., 10.);
+        assert!(c.look_from.y > 1.9 && c.look_from.y < 2.);
+        assert!(c.validate().is_ok());
+    }
+
+    #[test]
+    fn zoom_gets_closer() {
+        let mut c = camera();
+        zoom(&mut c, 1.);
+        assert!((c.look_from.z - 1.8).abs() < 1e-6);
+
+        zoom(&mut c, -1.);
+        assert!((c.look_from.z - 2.).abs() < 1e-6);
+
+        zoom(&mut c, 1000.);
+        assert!(c.validate().is_ok());
+    }
+}
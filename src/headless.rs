@@ -0,0 +1,139 @@
+use glam::Vec3;
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    cli::Args,
+    utils::{
+        aov::Aov,
+        image::{self, Image, WriteOptions},
+        tone_mapping::ToneMapping,
+    },
+    views::View,
+    ScreenChunk,
+};
+
+/// Runs a single step of the view and collects every chunk it sends, in linear colors.
+///
+/// Views drop their senders once done, so this returns when the frame is complete.
+pub fn render(view: &mut dyn View, width: u32, height: u32) -> Vec<Vec3> {
+    let (tx, rx) = std::sync::mpsc::channel::<ScreenChunk>();
+
+    view.step(tx, width, height);
+
+    let mut buffer = vec![Vec3::ZERO; (width * height) as usize];
+
+    for chunk in rx {
+        buffer[chunk.from..][..chunk.data.len()].copy_from_slice(chunk.data.as_slice());
+    }
+
+    buffer
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let scene = args.load_scene()?;
+    let tone_mapping = scene.tone_mapping;
+    let mut view = crate::views::RayTracingView::new(scene);
+
+    let start = std::time::Instant::now();
+    let buffer = render(&mut view, args.width, args.height);
+
+    let image = Image {
+        width: args.width as usize,
+        height: args.height as usize,
+        pixels: buffer,
+    };
+    let options = WriteOptions {
+        tone_mapping,
+        exr: args.exr,
+    };
+    image::write(&args.output, &image, &options)?;
+
+    println!(
+        "rendered {}x{} in {:.2?} to {}",
+        args.width,
+        args.height,
+        start.elapsed(),
+        args.output.display()
+    );
+
+    for aov in &args.aovs {
+        let path = aov_path(&args.output, *aov);
+
+        view.set_aov(Some(*aov));
+        let mut pixels = render(&mut view, args.width, args.height);
+        if !image::is_high_dynamic_range(&path) {
+            pixels.iter_mut().for_each(|p| *p = aov.display(*p));
+        }
+
+        let image = Image { pixels, ..image };
+        let options = WriteOptions {
+            tone_mapping: ToneMapping::default(),
+            exr: args.exr,
+        };
+        image::write(&path, &image, &options)?;
+        println!("wrote {aov} to {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// `render.png` becomes `render.albedo.png`
+fn aov_path(output: &Path, aov: Aov) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match output.extension() {
+        Some(extension) => format!("{stem}.{aov}.{}", extension.to_string_lossy()),
+        None => format!("{stem}.{aov}"),
+    };
+
+    output.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use std::path::{Path, PathBuf};
+
+    use super::{aov_path, render};
+    use crate::{
+        utils::{aov::Aov, scene_file::default_scene},
+        views::{ColorsView, RayTracingView},
+    };
+
+    #[test]
+    fn collects_every_chunk() {
+        let buffer = render(&mut ColorsView, 4, 3);
+
+        assert_eq!(buffer.len(), 12);
+        // red grows along x, green along y
+        assert_eq!(buffer[0], Vec3::ZERO);
+        assert_eq!(buffer[3].x, 0.75);
+        assert!(buffer[4].y > 0.);
+    }
+
+    #[test]
+    fn ray_tracing_fills_the_frame() {
+        let mut scene = default_scene();
+        scene.samples = 1;
+        scene.max_depth = 4;
+
+        let buffer = render(&mut RayTracingView::new(scene), 8, 6);
+
+        assert_eq!(buffer.len(), 48);
+        // The top row only sees the sky
+        assert!(buffer[..8].iter().all(|p| p.min_element() > 0.));
+    }
+
+    #[test]
+    fn output_variables_are_written_next_to_the_image() {
+        assert_eq!(
+            aov_path(Path::new("out/render.exr"), Aov::Normal),
+            PathBuf::from("out/render.normal.exr")
+        );
+        assert_eq!(
+            aov_path(Path::new("render"), Aov::ObjectId),
+            PathBuf::from("render.object_id")
+        );
+    }
+}
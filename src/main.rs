@@ -1,5 +1,8 @@
-#![feature(test, const_black_box)]
+#![cfg_attr(test, feature(test))]
 
+mod cli;
+mod controls;
+mod headless;
 pub mod utils;
 mod views;
 
@@ -9,51 +12,114 @@ use std::{
     time::Duration,
 };
 
+use glam::Vec3;
+use utils::{aov::Aov, tone_mapping::ToneMapping};
 use winit::{
     application::ApplicationHandler,
-    dpi::LogicalSize,
-    event::WindowEvent,
+    dpi::{LogicalSize, PhysicalPosition},
+    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
     window::Window,
 };
 
 fn main() -> anyhow::Result<()> {
+    let args = cli::Args::parse(std::env::args().skip(1))?;
+
+    if args.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+
+    if args.headless {
+        return headless::run(&args);
+    }
+
     let event_loop = EventLoop::new()?;
 
     event_loop.set_control_flow(ControlFlow::Wait);
 
-    let mut app = Application::default();
+    let mut app = Application::new(args)?;
 
     event_loop.run_app(&mut app)?;
 
     println!("main loop exit");
-    return Ok(());
+    Ok(())
 }
 
+/// Stops added or removed by the exposure keys
+const EXPOSURE_STEP: f32 = 0.5;
+
 struct Application {
+    args: cli::Args,
     window: Option<Arc<Window>>,
     renderer: Box<dyn views::View>,
-    outer_buffer: Arc<Mutex<Vec<u32>>>,
+    /// Linear colors, mapped to the screen on every redraw
+    outer_buffer: Arc<Mutex<Vec<Vec3>>>,
+    tone_mapping: Arc<Mutex<ToneMapping>>,
+    /// Output variable shown instead of the color
+    aov: Option<Aov>,
+    /// Starts from the scene file, then only changes with its key
+    denoise: bool,
     thread_id: Arc<Mutex<usize>>,
+    /// Last cursor position while the left button is held, for orbiting
+    drag: Option<PhysicalPosition<f64>>,
+    dragging: bool,
+    /// Yaw and pitch dragged since the last redraw, the render restarts once per frame
+    orbit: Option<(f32, f32)>,
 }
 
-impl Default for Application {
-    fn default() -> Self {
-        Self {
+impl Application {
+    fn new(args: cli::Args) -> anyhow::Result<Self> {
+        let scene = args.load_scene()?;
+        Ok(Self {
+            tone_mapping: Arc::new(Mutex::new(scene.tone_mapping)),
+            denoise: scene.denoise,
+            renderer: Box::new(views::RayTracingView::new(scene)),
+            args,
             window: None,
-            renderer: Box::new(views::RayTracingView::default()),
             outer_buffer: Arc::new(Mutex::new(vec![])),
+            aov: None,
             thread_id: Arc::new(Mutex::new(0)),
-        }
+            drag: None,
+            dragging: false,
+            orbit: None,
+        })
     }
 }
 
+const TITLE: &str = "esc quit | 1 colors | 2 ray tracing (reloads the scene) | wasd/qe move, drag orbit, scroll zoom | t tone mapping, +/- exposure | v output variables | n denoise";
+
 struct ScreenChunk {
     from: usize,
-    data: Vec<u32>,
+    /// Linear colors, 1 being white
+    data: Vec<Vec3>,
+    /// Samples per pixel accumulated so far, for progressive views
+    samples: Option<usize>,
 }
 
 impl Application {
+    /// Applies `control` to the camera of the current view, and restarts the rendering
+    fn move_camera(&mut self, control: impl FnOnce(&mut utils::camera::CameraBuilder)) {
+        if let Some(camera) = self.renderer.camera_mut() {
+            control(camera);
+            self.reload_scene();
+        }
+    }
+
+    /// Applies `change` to the tone mapping, which only needs a redraw of the current buffer
+    fn tone_map(&mut self, change: impl FnOnce(&mut ToneMapping)) {
+        change(&mut self.tone_mapping.lock().unwrap());
+        let window = self.window.as_ref().unwrap();
+        window.set_title(&title(
+            &self.tone_mapping.lock().unwrap(),
+            self.aov,
+            self.denoise,
+            None,
+        ));
+        window.request_redraw();
+    }
+
     fn reload_scene(&mut self) {
         /*
          * When reloading a scene, the old buffer can be cleaned up
@@ -74,13 +140,12 @@ impl Application {
         let height = size.height;
 
         {
-            let mut v = vec![];
-            v.resize(
+            let v = vec![
+                Vec3::ZERO;
                 (width * height)
                     .try_into()
-                    .expect("Width and height must be non negative"),
-                0u32,
-            );
+                    .expect("Width and height must be non negative")
+            ];
 
             self.outer_buffer = Arc::new(Mutex::new(v));
         }
@@ -91,6 +156,17 @@ impl Application {
 
         let buffer = self.outer_buffer.clone();
         let window = self.window.clone();
+        let tone_mapping = self.tone_mapping.clone();
+        let aov = self.aov;
+        let denoise = self.denoise;
+        window.as_ref().unwrap().set_title(&title(
+            &tone_mapping.lock().unwrap(),
+            aov,
+            denoise,
+            None,
+        ));
+
+        let mut shown_samples = None;
         std::thread::spawn(move || loop {
             {
                 // The
@@ -103,6 +179,16 @@ impl Application {
                 Ok(chunk) => {
                     buffer.lock().unwrap()[chunk.from..][..chunk.data.len()]
                         .copy_from_slice(chunk.data.as_slice());
+
+                    if let Some(samples) = chunk.samples.filter(|s| Some(*s) != shown_samples) {
+                        shown_samples = Some(samples);
+                        window.as_ref().unwrap().set_title(&title(
+                            &tone_mapping.lock().unwrap(),
+                            aov,
+                            denoise,
+                            shown_samples,
+                        ));
+                    }
                 }
                 Err(TryRecvError::Empty) => {
                     window.as_ref().unwrap().request_redraw();
@@ -116,6 +202,29 @@ impl Application {
     }
 }
 
+fn title(
+    tone_mapping: &ToneMapping,
+    aov: Option<Aov>,
+    denoise: bool,
+    samples: Option<usize>,
+) -> String {
+    let mut title = match aov {
+        Some(aov) => format!("{TITLE} | {aov}"),
+        None => format!(
+            "{TITLE} | {} {:+} EV",
+            tone_mapping.tone_mapper, tone_mapping.exposure
+        ),
+    };
+    // Output variables are never denoised
+    if denoise && aov.is_none() {
+        title += " | denoised";
+    }
+    if let Some(samples) = samples {
+        title += &format!(" | {samples} spp");
+    }
+    title
+}
+
 impl ApplicationHandler for Application {
     fn window_event(
         &mut self,
@@ -129,21 +238,90 @@ impl ApplicationHandler for Application {
                 event_loop.exit();
             }
             WindowEvent::KeyboardInput { event, .. } => {
+                if event.state == ElementState::Pressed {
+                    let direction = match event.physical_key {
+                        PhysicalKey::Code(KeyCode::KeyW) => Some(Vec3::Z),
+                        PhysicalKey::Code(KeyCode::KeyS) => Some(Vec3::NEG_Z),
+                        PhysicalKey::Code(KeyCode::KeyD) => Some(Vec3::X),
+                        PhysicalKey::Code(KeyCode::KeyA) => Some(Vec3::NEG_X),
+                        PhysicalKey::Code(KeyCode::KeyE) => Some(Vec3::Y),
+                        PhysicalKey::Code(KeyCode::KeyQ) => Some(Vec3::NEG_Y),
+                        _ => None,
+                    };
+
+                    if let Some(direction) = direction {
+                        self.move_camera(|camera| controls::translate(camera, direction));
+                        return;
+                    }
+
+                    let exposure = match event.logical_key.as_ref() {
+                        winit::keyboard::Key::Character("+" | "=") => Some(EXPOSURE_STEP),
+                        winit::keyboard::Key::Character("-") => Some(-EXPOSURE_STEP),
+                        _ => None,
+                    };
+
+                    if let Some(exposure) = exposure {
+                        self.tone_map(|tone_mapping| tone_mapping.exposure += exposure);
+                        return;
+                    }
+                    if event.logical_key.as_ref() == winit::keyboard::Key::Character("t") {
+                        self.tone_map(|tone_mapping| {
+                            tone_mapping.tone_mapper = tone_mapping.tone_mapper.next()
+                        });
+                        return;
+                    }
+                }
+
+                // Releases and held keys would switch the view, output variable or denoiser again
+                if event.state != ElementState::Pressed || event.repeat {
+                    return;
+                }
+
                 match event.logical_key.as_ref() {
                     winit::keyboard::Key::Character("1") => {
                         self.renderer = Box::new(views::ColorsView);
+                        self.aov = None;
                     }
                     winit::keyboard::Key::Character("2") => {
-                        self.renderer = Box::new(views::RayTracingView::default());
+                        // Reloading the file allows to edit the scene while the window is open
+                        match self.args.load_scene() {
+                            Ok(scene) => {
+                                *self.tone_mapping.lock().unwrap() = scene.tone_mapping;
+                                self.renderer = Box::new(views::RayTracingView::new(scene));
+                                self.renderer.set_aov(self.aov);
+                                self.renderer.set_denoise(self.denoise);
+                            }
+                            Err(e) => eprintln!("{e:#}"),
+                        }
+                    }
+                    winit::keyboard::Key::Character("v") => {
+                        self.aov = Aov::next(self.aov);
+                        self.renderer.set_aov(self.aov);
+                    }
+                    winit::keyboard::Key::Character("n") => {
+                        self.denoise = !self.denoise;
+                        self.renderer.set_denoise(self.denoise);
+                    }
+                    // q moves the camera down, so escape quits
+                    winit::keyboard::Key::Named(winit::keyboard::NamedKey::Escape) => {
+                        std::process::exit(0)
                     }
-                    winit::keyboard::Key::Character("q") => std::process::exit(0),
                     _ => {}
                 };
                 self.reload_scene()
             }
             WindowEvent::RedrawRequested => {
+                if let Some((yaw, pitch)) = self.orbit.take() {
+                    self.move_camera(|camera| controls::orbit(camera, yaw, pitch));
+                }
+
                 let tmp_buf = self.outer_buffer.clone();
                 let tmp_buf = tmp_buf.lock().unwrap();
+                // Output variables are not light, they are shown as they are
+                let tone_mapping = match self.aov {
+                    Some(_) => ToneMapping::default(),
+                    None => *self.tone_mapping.lock().unwrap(),
+                };
 
                 let context = softbuffer::Context::new(window.clone()).unwrap();
                 let mut surface = softbuffer::Surface::new(&context, window.clone()).unwrap();
@@ -165,7 +343,12 @@ impl ApplicationHandler for Application {
                 if buffer.len() != tmp_buf.len() {
                     return;
                 }
-                buffer.copy_from_slice(&tmp_buf);
+                for (pixel, color) in buffer.iter_mut().zip(tmp_buf.iter()) {
+                    *pixel = match self.aov {
+                        Some(aov) => tone_mapping.color(aov.display(*color)),
+                        None => tone_mapping.color(*color),
+                    };
+                }
                 buffer.present().unwrap();
                 // Do not care of more than 60 fps
                 std::thread::sleep(Duration::from_millis(1000 / 60));
@@ -174,6 +357,37 @@ impl ApplicationHandler for Application {
             WindowEvent::Resized(_) => {
                 self.reload_scene();
             }
+
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.dragging = state == ElementState::Pressed;
+                self.drag = None;
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if !self.dragging {
+                    return;
+                }
+
+                if let Some(last) = self.drag.replace(position) {
+                    let yaw = (position.x - last.x) as f32 * controls::ORBIT_SENSITIVITY;
+                    let pitch = (position.y - last.y) as f32 * controls::ORBIT_SENSITIVITY;
+                    let (total_yaw, total_pitch) = self.orbit.get_or_insert((0., 0.));
+                    *total_yaw += yaw;
+                    *total_pitch += pitch;
+                    window.request_redraw();
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    // Roughly the height of a line of text
+                    MouseScrollDelta::PixelDelta(p) => p.y as f32 / 20.,
+                };
+                self.move_camera(|camera| controls::zoom(camera, lines));
+            }
             _ => {}
         }
     }
@@ -181,7 +395,7 @@ impl ApplicationHandler for Application {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         println!("resumed");
         let window_attributes = Window::default_attributes()
-            .with_title("press: q to quit | 1 colors | 2 ray tracing")
+            .with_title(TITLE)
             .with_inner_size(LogicalSize::new(800, 600));
 
         let window = Arc::new(
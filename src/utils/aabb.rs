@@ -0,0 +1,168 @@
+use glam::Vec3;
+
+/// Axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl Aabb {
+    /// Contains nothing, neutral element of [`Aabb::union`]
+    pub const EMPTY: Aabb = Aabb {
+        min: Vec3::INFINITY,
+        max: Vec3::NEG_INFINITY,
+    };
+
+    /// Contains everything, for meshes without finite bounds like infinite planes
+    pub const INFINITE: Aabb = Aabb {
+        min: Vec3::NEG_INFINITY,
+        max: Vec3::INFINITY,
+    };
+
+    /// Box spanning two corners, in any order
+    pub fn new(a: Vec3, b: Vec3) -> Aabb {
+        Aabb {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Aabb {
+        points
+            .into_iter()
+            .fold(Aabb::EMPTY, |aabb, point| aabb.grow(point))
+    }
+
+    #[must_use]
+    pub fn grow(&self, point: Vec3) -> Aabb {
+        Aabb {
+            min: self.min.min(point),
+            max: self.max.max(point),
+        }
+    }
+
+    #[must_use]
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        std::array::from_fn(|corner| {
+            let pick = |bit: usize, min: f32, max: f32| if corner & bit == 0 { min } else { max };
+            Vec3::new(
+                pick(1, self.min.x, self.max.x),
+                pick(2, self.min.y, self.max.y),
+                pick(4, self.min.z, self.max.z),
+            )
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.min.is_finite() && self.max.is_finite()
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) / 2.
+    }
+
+    pub fn extent(&self) -> Vec3 {
+        (self.max - self.min).max(Vec3::ZERO)
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let e = self.extent();
+        2. * (e.x * e.y + e.y * e.z + e.z * e.x)
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        let e = self.extent();
+        if e.x >= e.y && e.x >= e.z {
+            0
+        } else if e.y >= e.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Slab test, `inverse_direction` is `1 / ray.direction` computed once per ray
+    pub fn hit(
+        &self,
+        origin: Vec3,
+        inverse_direction: Vec3,
+        ray_t_min: f32,
+        ray_t_max: f32,
+    ) -> bool {
+        let t0 = (self.min - origin) * inverse_direction;
+        let t1 = (self.max - origin) * inverse_direction;
+
+        let t_near = t0.min(t1).max_element().max(ray_t_min);
+        let t_far = t0.max(t1).min_element().min(ray_t_max);
+
+        t_near <= t_far
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::Aabb;
+
+    fn unit() -> Aabb {
+        Aabb::new(Vec3::ONE, Vec3::ZERO)
+    }
+
+    #[test]
+    fn construction() {
+        let aabb = unit();
+        assert_eq!(aabb.min, Vec3::ZERO);
+        assert_eq!(aabb.max, Vec3::ONE);
+        assert_eq!(aabb.surface_area(), 6.);
+
+        assert!(Aabb::EMPTY.is_empty());
+        assert_eq!(Aabb::EMPTY.union(&aabb), aabb);
+        assert_eq!(
+            Aabb::from_points([Vec3::new(0., 2., 0.), Vec3::new(1., 0., -1.)]).longest_axis(),
+            1
+        );
+    }
+
+    #[test]
+    fn slab_test() {
+        let aabb = unit();
+        let origin = Vec3::new(0.5, 0.5, 5.);
+
+        let towards = Vec3::new(0., 0., -1.).recip();
+        assert!(aabb.hit(origin, towards, 0.001, f32::INFINITY));
+        assert!(!aabb.hit(origin, towards, 0.001, 3.));
+
+        let away = Vec3::new(0., 0., 1.).recip();
+        assert!(!aabb.hit(origin, away, 0.001, f32::INFINITY));
+
+        let beside = Vec3::new(2., 0.5, 5.);
+        assert!(!aabb.hit(beside, towards, 0.001, f32::INFINITY));
+    }
+
+    #[test]
+    fn flat_boxes_are_hit() {
+        let flat = Aabb::new(Vec3::new(0., 0., -1.), Vec3::new(1., 1., -1.));
+        let direction = Vec3::new(0., 0., -1.).recip();
+
+        assert!(flat.hit(Vec3::new(0.5, 0.5, 0.), direction, 0.001, f32::INFINITY));
+    }
+}
@@ -0,0 +1,174 @@
+//! Arbitrary output variables, what the camera rays hit first instead of the lit image
+
+use std::{fmt, str::FromStr};
+
+use anyhow::bail;
+use glam::Vec3;
+
+/// Buffer rendered next to, or instead of, the color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    /// Color of the surface without any lighting, see [`Material::albedo`](super::materials::Material::albedo)
+    Albedo,
+    /// Shading normal in world space, facing the camera
+    Normal,
+    /// Distance from the camera, infinite for the background
+    Depth,
+    /// Id given by [`Scene::add_material`](super::scene::Scene::add_material), 0 for the others
+    MaterialId,
+    /// Index of the object in the scene plus one, 0 for the background
+    ObjectId,
+}
+
+/// What a camera ray hits first
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FirstHit {
+    pub albedo: Vec3,
+    pub normal: Vec3,
+    pub depth: f32,
+    pub material_id: usize,
+    pub object_id: usize,
+}
+
+impl FirstHit {
+    pub const BACKGROUND: FirstHit = FirstHit {
+        albedo: Vec3::ZERO,
+        normal: Vec3::ZERO,
+        depth: f32::INFINITY,
+        material_id: 0,
+        object_id: 0,
+    };
+}
+
+impl Aov {
+    pub const ALL: [Aov; 5] = [
+        Aov::Albedo,
+        Aov::Normal,
+        Aov::Depth,
+        Aov::MaterialId,
+        Aov::ObjectId,
+    ];
+
+    /// The one after `aov` to cycle through them, `None` being the color
+    pub fn next(aov: Option<Aov>) -> Option<Aov> {
+        match aov {
+            None => Some(Self::ALL[0]),
+            Some(aov) => {
+                let index = Self::ALL.iter().position(|a| *a == aov).unwrap_or(0);
+                Self::ALL.get(index + 1).copied()
+            }
+        }
+    }
+
+    /// Whether samples can be averaged, ids and depths across an edge mean nothing
+    pub fn is_averaged(self) -> bool {
+        matches!(self, Aov::Albedo | Aov::Normal)
+    }
+
+    /// Value written to high dynamic range images, scalars are repeated in every channel
+    pub fn value(self, hit: &FirstHit) -> Vec3 {
+        match self {
+            Aov::Albedo => hit.albedo,
+            Aov::Normal => hit.normal,
+            Aov::Depth => Vec3::splat(hit.depth),
+            Aov::MaterialId => Vec3::splat(hit.material_id as f32),
+            Aov::ObjectId => Vec3::splat(hit.object_id as f32),
+        }
+    }
+
+    /// Maps a [`Aov::value`] to a color between 0 and 1 that can be looked at
+    pub fn display(self, value: Vec3) -> Vec3 {
+        match self {
+            Aov::Albedo => value,
+            // The background has no normal
+            Aov::Normal if value == Vec3::ZERO => value,
+            Aov::Normal => value * 0.5 + 0.5,
+            // Closer is brighter, the background is black
+            Aov::Depth => Vec3::splat(1. / (1. + value.x)),
+            Aov::MaterialId | Aov::ObjectId => id_color(value.x as usize),
+        }
+    }
+}
+
+/// Distinct color for every id, black for 0
+fn id_color(id: usize) -> Vec3 {
+    if id == 0 {
+        return Vec3::ZERO;
+    }
+
+    let hash = (id as u32).wrapping_mul(2654435761);
+    let [r, g, b, _] = hash.to_be_bytes();
+    Vec3::new(r as f32, g as f32, b as f32) / 255.
+}
+
+impl fmt::Display for Aov {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::MaterialId => "material_id",
+            Aov::ObjectId => "object_id",
+        })
+    }
+}
+
+impl FromStr for Aov {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> anyhow::Result<Aov> {
+        match Self::ALL.into_iter().find(|a| a.to_string() == name) {
+            Some(aov) => Ok(aov),
+            None => bail!(
+                "unknown output variable `{name}`, expected albedo, normal, depth, material_id or object_id"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::{Aov, FirstHit};
+
+    #[test]
+    fn values_and_display() {
+        let hit = FirstHit {
+            albedo: Vec3::new(0.5, 0.25, 1.),
+            normal: Vec3::NEG_Y,
+            depth: 3.,
+            material_id: 2,
+            object_id: 7,
+        };
+
+        assert_eq!(
+            Aov::Normal.display(Aov::Normal.value(&hit)),
+            Vec3::new(0.5, 0., 0.5)
+        );
+        assert_eq!(
+            Aov::Depth.display(Aov::Depth.value(&hit)),
+            Vec3::splat(0.25)
+        );
+        assert_eq!(Aov::ObjectId.value(&hit), Vec3::splat(7.));
+
+        let background = FirstHit::BACKGROUND;
+        for aov in Aov::ALL {
+            assert_eq!(aov.display(aov.value(&background)), Vec3::ZERO);
+            assert_eq!(aov.to_string().parse::<Aov>().unwrap(), aov);
+        }
+        assert_ne!(
+            Aov::ObjectId.display(Vec3::splat(1.)),
+            Aov::ObjectId.display(Vec3::splat(2.))
+        );
+
+        // Cycling goes back to the color after the last one
+        let mut cycle = vec![];
+        let mut aov = Aov::next(None);
+        while let Some(current) = aov {
+            cycle.push(current);
+            aov = Aov::next(aov);
+        }
+        assert_eq!(cycle, Aov::ALL);
+    }
+}
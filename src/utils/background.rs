@@ -0,0 +1,288 @@
+use std::{
+    f32::consts::{PI, TAU},
+    sync::Arc,
+};
+
+use glam::Vec3;
+
+use super::{
+    colors::{self, ColorVec, SKY_BLUE, WHITE},
+    image::Image,
+};
+
+/// Light coming from every direction no object is hit in
+#[derive(Debug, Clone, PartialEq)]
+pub enum Background {
+    /// Same color everywhere, black makes the lights in the scene the only source of light
+    Solid(ColorVec),
+    /// Blends from `bottom` looking straight down to `top` looking straight up
+    Gradient { bottom: ColorVec, top: ColorVec },
+    /// Surrounding image, sampled like the lights of the scene
+    Environment(Arc<Environment>),
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Gradient {
+            bottom: WHITE,
+            top: SKY_BLUE,
+        }
+    }
+}
+
+impl Background {
+    pub const BLACK: Background = Background::Solid(Vec3::ZERO);
+
+    pub fn color(&self, direction: Vec3) -> ColorVec {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let a = (direction.normalize().y + 1.) / 2.;
+                (1. - a) * bottom + a * top
+            }
+            Background::Environment(environment) => environment.color(direction),
+        }
+    }
+
+    /// Whether [`Background::sample`] can be used
+    pub fn can_sample(&self) -> bool {
+        matches!(self, Background::Environment(_))
+    }
+
+    /// Random direction, more likely towards the bright parts
+    pub fn sample(&self) -> Option<Vec3> {
+        match self {
+            Background::Environment(environment) => environment.sample(),
+            _ => None,
+        }
+    }
+
+    /// Density, in solid angle, of [`Background::sample`] returning `direction`
+    pub fn pdf(&self, direction: Vec3) -> f32 {
+        match self {
+            Background::Environment(environment) => environment.pdf(direction),
+            _ => 0.,
+        }
+    }
+}
+
+/// Equirectangular image around the scene, the center of the image is towards -Z
+/// and the top row straight up.
+///
+/// Directions are importance sampled pixel by pixel proportionally to their
+/// luminance, so small bright spots like the sun are found by the shadow rays.
+#[derive(Debug, PartialEq)]
+pub struct Environment {
+    image: Image,
+    /// Around the vertical axis, in radians
+    rotation: f32,
+    /// Luminance of each pixel times the solid angle it covers
+    weights: Vec<f32>,
+    total: f32,
+    /// Cumulative distribution of picking each row
+    rows: Vec<f32>,
+    /// Cumulative distribution of picking each pixel within its row
+    columns: Vec<f32>,
+}
+
+impl Environment {
+    /// `image` holds radiance where 1 is white, `rotation` is in degrees
+    pub fn new(mut image: Image, intensity: f32, rotation: f32) -> Environment {
+        image
+            .pixels
+            .iter_mut()
+            .for_each(|p| *p = (*p * intensity * 255.).max(Vec3::ZERO));
+
+        let (width, height) = (image.width, image.height);
+
+        let weights: Vec<f32> = image
+            .pixels
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let theta = PI * ((i / width) as f32 + 0.5) / height as f32;
+                colors::luminance(*p) * theta.sin()
+            })
+            .collect();
+
+        let mut rows = Vec::with_capacity(height);
+        let mut columns = Vec::with_capacity(width * height);
+        let mut total = 0.;
+
+        for row in weights.chunks(width.max(1)) {
+            let row_total: f32 = row.iter().sum();
+
+            let mut sum = 0.;
+            columns.extend(row.iter().map(|w| {
+                sum += w;
+                if row_total > 0. {
+                    sum / row_total
+                } else {
+                    0.
+                }
+            }));
+
+            total += row_total;
+            rows.push(total);
+        }
+
+        if total > 0. {
+            rows.iter_mut().for_each(|r| *r /= total);
+        }
+
+        Environment {
+            image,
+            rotation: rotation.to_radians(),
+            weights,
+            total,
+            rows,
+            columns,
+        }
+    }
+
+    /// Pixel seen in `direction`
+    fn pixel(&self, direction: Vec3) -> (usize, usize) {
+        let d = direction.normalize();
+
+        let phi = d.x.atan2(-d.z) - self.rotation;
+        let u = (phi / TAU + 0.5).rem_euclid(1.);
+        let v = d.y.clamp(-1., 1.).acos() / PI;
+
+        let x = ((u * self.image.width as f32) as usize).min(self.image.width - 1);
+        let y = ((v * self.image.height as f32) as usize).min(self.image.height - 1);
+        (x, y)
+    }
+
+    pub fn color(&self, direction: Vec3) -> ColorVec {
+        let (x, y) = self.pixel(direction);
+        self.image.pixel(x, y)
+    }
+
+    pub fn sample(&self) -> Option<Vec3> {
+        if self.total <= 0. {
+            return None;
+        }
+
+        let pick = |cdf: &[f32]| {
+            let r = rand::random::<f32>();
+            cdf.partition_point(|&c| c <= r).min(cdf.len() - 1)
+        };
+
+        let width = self.image.width;
+        let y = pick(&self.rows);
+        let x = pick(&self.columns[y * width..][..width]);
+
+        let u = (x as f32 + rand::random::<f32>()) / width as f32;
+        let v = (y as f32 + rand::random::<f32>()) / self.image.height as f32;
+
+        let phi = (u - 0.5) * TAU + self.rotation;
+        let theta = v * PI;
+
+        Some(Vec3::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        ))
+    }
+
+    pub fn pdf(&self, direction: Vec3) -> f32 {
+        if self.total <= 0. {
+            return 0.;
+        }
+
+        let sin_theta = (1. - direction.normalize().y.powi(2)).max(0.).sqrt();
+        if sin_theta <= 0. {
+            return 0.;
+        }
+
+        let (x, y) = self.pixel(direction);
+        let pixels = (self.image.width * self.image.height) as f32;
+
+        // Uniform within the pixel, then from the image to the sphere
+        self.weights[y * self.image.width + x] / self.total * pixels / (2. * PI * PI * sin_theta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use glam::Vec3;
+
+    use super::{Background, Environment};
+    use crate::utils::{
+        colors::{SKY_BLUE, WHITE},
+        image::Image,
+        random_unit_vector,
+    };
+
+    #[test]
+    fn gradient_blends_vertically() {
+        let sky = Background::default();
+
+        assert_eq!(sky.color(Vec3::Y), SKY_BLUE);
+        assert_eq!(sky.color(Vec3::NEG_Y * 3.), WHITE);
+        assert_eq!(sky.color(Vec3::X), (WHITE + SKY_BLUE) / 2.);
+
+        assert_eq!(Background::BLACK.color(Vec3::Y), Vec3::ZERO);
+        assert!(!sky.can_sample());
+    }
+
+    fn environment() -> Environment {
+        // 8x4, dim everywhere but a bright pixel above the horizon, behind the camera
+        let mut pixels = vec![Vec3::splat(0.1); 32];
+        pixels[8] = Vec3::splat(100.);
+
+        Environment::new(
+            Image {
+                width: 8,
+                height: 4,
+                pixels,
+            },
+            1.,
+            0.,
+        )
+    }
+
+    #[test]
+    fn equirectangular_lookup() {
+        let environment = environment();
+
+        assert_eq!(environment.pixel(Vec3::NEG_Z), (4, 2));
+        assert_eq!(environment.pixel(Vec3::new(0., 0.999, -0.01)), (4, 0));
+        assert_eq!(environment.pixel(Vec3::new(-0.001, 0.3, 0.5)), (0, 1));
+        assert_eq!(
+            environment.color(Vec3::new(-0.001, 0.3, 0.5)),
+            Vec3::splat(25500.)
+        );
+        assert_eq!(environment.color(Vec3::NEG_Z), Vec3::splat(25.5));
+
+        let rotated = Environment::new(environment.image.clone(), 1. / 255., 90.);
+        assert_eq!(rotated.pixel(Vec3::X), (4, 2));
+    }
+
+    #[test]
+    fn sampling_matches_the_pdf() {
+        let environment = environment();
+
+        // The bright pixel is found most of the time
+        let bright = (0..1000)
+            .filter(|_| environment.pixel(environment.sample().unwrap()) == (0, 1))
+            .count();
+        assert!(bright > 900, "{bright}");
+
+        // Integrating the pdf over the sphere gives 1
+        let n = 200_000;
+        let integral = (0..n)
+            .map(|_| environment.pdf(random_unit_vector()))
+            .sum::<f32>()
+            * 4.
+            * PI
+            / n as f32;
+        assert!((integral - 1.).abs() < 0.05, "{integral}");
+
+        for _ in 0..100 {
+            assert!(environment.pdf(environment.sample().unwrap()) > 0.);
+        }
+    }
+}
@@ -6,29 +6,242 @@ pub struct Camera {
     pub delta_u: Vec3,
     pub delta_v: Vec3,
     pub upper_left: Vec3,
+    /// Radius of the lens along the horizontal and vertical axis, zero without depth of field
+    pub defocus_disk_u: Vec3,
+    pub defocus_disk_v: Vec3,
+    pub shutter_open: f32,
+    pub shutter_close: f32,
 }
 
 impl Camera {
     pub fn new(center: Vec3, width: u32, height: u32) -> Camera {
+        CameraBuilder::default()
+            .look_from(center)
+            .look_at(center - Vec3::Z)
+            .build(width, height)
+    }
+
+    pub fn has_defocus_blur(&self) -> bool {
+        self.defocus_disk_u != Vec3::ZERO
+    }
+
+    /// Instant for a new ray, anywhere while the shutter is open
+    pub fn random_time(&self) -> f32 {
+        if self.shutter_open < self.shutter_close {
+            rand::random_range(self.shutter_open..self.shutter_close)
+        } else {
+            self.shutter_open
+        }
+    }
+}
+
+/// Describes a camera independently of the image size
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraBuilder {
+    pub look_from: Vec3,
+    pub look_at: Vec3,
+    pub vup: Vec3,
+    /// Vertical field of view, in degrees
+    pub vertical_fov: f32,
+    /// Angle of the cone from the focus plane to the lens, in degrees. 0 disables depth of field
+    pub defocus_angle: f32,
+    /// Distance of the plane in perfect focus, defaults to the distance to `look_at`
+    pub focus_distance: Option<f32>,
+    /// Interval the shutter stays open, moving objects are blurred along their motion
+    /// in it. Objects are placed at time 0 and moved by time 1
+    pub shutter: (f32, f32),
+}
+
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        Self {
+            look_from: Vec3::ZERO,
+            look_at: Vec3::NEG_Z,
+            vup: Vec3::Y,
+            vertical_fov: 90.,
+            defocus_angle: 0.,
+            focus_distance: None,
+            shutter: (0., 0.),
+        }
+    }
+}
+
+impl CameraBuilder {
+    pub fn look_from(mut self, look_from: Vec3) -> Self {
+        self.look_from = look_from;
+        self
+    }
+
+    pub fn look_at(mut self, look_at: Vec3) -> Self {
+        self.look_at = look_at;
+        self
+    }
+
+    pub fn vup(mut self, vup: Vec3) -> Self {
+        self.vup = vup;
+        self
+    }
+
+    pub fn vertical_fov(mut self, degrees: f32) -> Self {
+        self.vertical_fov = degrees;
+        self
+    }
+
+    pub fn defocus_angle(mut self, degrees: f32) -> Self {
+        self.defocus_angle = degrees;
+        self
+    }
+
+    pub fn focus_distance(mut self, distance: f32) -> Self {
+        self.focus_distance = Some(distance);
+        self
+    }
+
+    pub fn shutter(mut self, open: f32, close: f32) -> Self {
+        self.shutter = (open, close);
+        self
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.look_from != self.look_at,
+            "look_from and look_at must be different points"
+        );
+        anyhow::ensure!(
+            self.vup.cross(self.look_from - self.look_at) != Vec3::ZERO,
+            "vup must not be parallel to the view direction"
+        );
+        anyhow::ensure!(
+            0. < self.vertical_fov && self.vertical_fov < 180.,
+            "the vertical field of view must be between 0 and 180 degrees"
+        );
+        anyhow::ensure!(
+            (0. ..180.).contains(&self.defocus_angle),
+            "the defocus angle must be between 0 and 180 degrees"
+        );
+        anyhow::ensure!(
+            self.focus_distance.is_none_or(|d| d > 0.),
+            "the focus distance must be greater than 0"
+        );
+        anyhow::ensure!(
+            self.shutter.0 <= self.shutter.1,
+            "the shutter must open before it closes"
+        );
+
+        Ok(())
+    }
+
+    pub fn build(&self, width: u32, height: u32) -> Camera {
         let aspect_ratio = width as f32 / height as f32;
 
-        let focal_length = 1.;
-        let viewport_height = 2.;
+        let focus_distance = self
+            .focus_distance
+            .unwrap_or_else(|| (self.look_from - self.look_at).length());
+
+        let h = (self.vertical_fov.to_radians() / 2.).tan();
+        let viewport_height = 2. * h * focus_distance;
         let viewport_width = viewport_height * aspect_ratio;
 
-        let u = Vec3::new(viewport_width, 0., 0.);
-        let v = Vec3::new(0., -viewport_height, 0.);
+        // Orthonormal basis, w points behind the camera
+        let w = (self.look_from - self.look_at).normalize();
+        let u = self.vup.cross(w).normalize();
+        let v = w.cross(u);
 
-        let delta_u = u / width as f32;
-        let delta_v = v / height as f32;
+        let viewport_u = viewport_width * u;
+        let viewport_v = viewport_height * -v;
 
-        let upper_left = center - Vec3::new(0., 0., focal_length) - u / 2. - v / 2.;
+        let delta_u = viewport_u / width as f32;
+        let delta_v = viewport_v / height as f32;
 
-        Self {
-            center,
+        let upper_left = self.look_from - focus_distance * w - viewport_u / 2. - viewport_v / 2.;
+
+        let defocus_radius = focus_distance * (self.defocus_angle.to_radians() / 2.).tan();
+
+        Camera {
+            center: self.look_from,
             delta_u,
             delta_v,
             upper_left,
+            defocus_disk_u: u * defocus_radius,
+            defocus_disk_v: v * defocus_radius,
+            shutter_open: self.shutter.0,
+            shutter_close: self.shutter.1,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::{Camera, CameraBuilder};
+
+    #[test]
+    fn default_is_the_old_fixed_camera() {
+        let camera = Camera::new(Vec3::ZERO, 200, 100);
+
+        assert_eq!(camera.center, Vec3::ZERO);
+        assert!(camera.upper_left.abs_diff_eq(Vec3::new(-2., 1., -1.), 1e-6));
+        assert!(camera.delta_u.abs_diff_eq(Vec3::new(0.02, 0., 0.), 1e-6));
+        assert!(camera.delta_v.abs_diff_eq(Vec3::new(0., -0.02, 0.), 1e-6));
+        assert!(!camera.has_defocus_blur());
+    }
+
+    #[test]
+    fn looks_at_the_target() {
+        let builder = CameraBuilder::default()
+            .look_from(Vec3::new(3., 3., 3.))
+            .look_at(Vec3::new(0., 1., 0.))
+            .vertical_fov(40.);
+        let camera = builder.build(100, 100);
+
+        let middle = camera.upper_left + 50. * camera.delta_u + 50. * camera.delta_v;
+        let direction = (middle - camera.center).normalize();
+        let expected = (builder.look_at - builder.look_from).normalize();
+
+        assert!(direction.abs_diff_eq(expected, 1e-5));
+        // Up in the image is up in the world
+        assert!(camera.delta_v.y < 0.);
+    }
+
+    #[test]
+    fn defocus_disk_size() {
+        let camera = CameraBuilder::default()
+            .defocus_angle(90.)
+            .focus_distance(2.)
+            .build(10, 10);
+
+        assert!(camera.has_defocus_blur());
+        assert!((camera.defocus_disk_u.length() - 2.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn rays_are_timed_within_the_shutter() {
+        let still = CameraBuilder::default().build(10, 10);
+        assert_eq!(still.random_time(), 0.);
+
+        let camera = CameraBuilder::default().shutter(0.25, 0.5).build(10, 10);
+        assert!((0..100)
+            .map(|_| camera.random_time())
+            .all(|t| (0.25..0.5).contains(&t)));
+    }
+
+    #[test]
+    fn invalid_cameras_are_rejected() {
+        assert!(CameraBuilder::default().validate().is_ok());
+        assert!(CameraBuilder::default()
+            .look_at(Vec3::ZERO)
+            .validate()
+            .is_err());
+        assert!(CameraBuilder::default().vup(Vec3::Z).validate().is_err());
+        assert!(CameraBuilder::default().shutter(1., 0.).validate().is_err());
+        assert!(CameraBuilder::default()
+            .vertical_fov(180.)
+            .validate()
+            .is_err());
+        assert!(CameraBuilder::default()
+            .focus_distance(0.)
+            .validate()
+            .is_err());
+    }
+}
@@ -3,12 +3,8 @@ use glam::Vec3;
 pub type Color = u32;
 pub type ColorVec = Vec3;
 
-pub fn vec3àto_color(v: &ColorVec) -> Color {
-    // vec3àto_color_uncorrected(v)
-    vec3àto_color_uncorrected(&((v / 255.).powf(0.5) * 255.))
-}
-
-const fn vec3àto_color_uncorrected(v: &ColorVec) -> Color {
+/// Packs a color from 0 to 255 in the `0x00RRGGBB` format of the window
+pub const fn vec3àto_color_uncorrected(v: &ColorVec) -> Color {
     v.z.clamp(0., 255.) as u32
         | ((v.y.clamp(0., 255.) as u32) << 8)
         | ((v.x.clamp(0., 255.) as u32) << 16)
@@ -18,6 +14,29 @@ pub fn vec3_to_scalar(v: &ColorVec) -> Vec3 {
     v / 255.
 }
 
+/// Decodes a channel of an 8 bit image, from 0 to 1, to linear light
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a linear channel, from 0 to 1, for an sRGB display
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// Perceived brightness of a linear color
+pub fn luminance(c: Vec3) -> f32 {
+    c.dot(Vec3::new(0.2126, 0.7152, 0.0722))
+}
+
 pub const BLACK: ColorVec = Vec3::new(0.01, 0.01, 0.01);
 pub const WHITE: ColorVec = Vec3::new(255., 255., 255.);
 
@@ -0,0 +1,270 @@
+//! Edge avoiding à-trous wavelet filter, from "Edge-Avoiding À-Trous Wavelet
+//! Transform for fast Global Illumination Filtering" by Dammertz et al.
+//!
+//! A 5x5 blur is applied a few times with holes growing between its taps, so
+//! that the last pass reaches far for the cost of 25 taps. Taps across an edge
+//! of the albedo, normal or depth guides are ignored, so noise is smoothed away
+//! while the edges of the scene stay sharp.
+
+use glam::Vec3;
+
+use super::aov::FirstHit;
+
+/// B3 spline, the weights of the taps along each axis
+const KERNEL: [f32; 5] = [1. / 16., 1. / 4., 3. / 8., 1. / 4., 1. / 16.];
+/// Taps are `2^i` pixels apart on the `i`th pass
+const PASSES: usize = 5;
+
+/// Tolerance to differences of the (compressed) lighting, halved at every pass
+const SIGMA_COLOR: f32 = 0.6;
+const SIGMA_ALBEDO: f32 = 0.1;
+/// The cosine between normals is squared this many times, higher is pickier
+const NORMAL_SQUARINGS: usize = 6;
+/// Tolerance to depth differences, relative to the depth and to the distance of the tap
+const SIGMA_DEPTH: f32 = 0.05;
+
+/// What the filter knows about the surface seen by a pixel, averaged over its samples
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Guide {
+    pub albedo: Vec3,
+    pub normal: Vec3,
+    /// Infinite for the background
+    pub depth: f32,
+}
+
+impl From<FirstHit> for Guide {
+    fn from(hit: FirstHit) -> Guide {
+        Guide {
+            albedo: hit.albedo,
+            normal: hit.normal,
+            depth: hit.depth,
+        }
+    }
+}
+
+/// Smooths the noise of a `width` x `height` linear image, `guides` has one entry per pixel
+pub fn denoise(width: usize, height: usize, color: &[Vec3], guides: &[Guide]) -> Vec<Vec3> {
+    assert_eq!(color.len(), width * height);
+    assert_eq!(guides.len(), width * height);
+    // A minimized window has no pixels, and rows cannot be split in empty bands
+    if color.is_empty() {
+        return vec![];
+    }
+
+    // The lighting is filtered without the texture, which is put back at the end
+    let mut lighting = color
+        .iter()
+        .zip(guides)
+        .map(|(color, guide)| *color / albedo(guide))
+        .collect::<Vec<_>>();
+
+    let threads = std::thread::available_parallelism()
+        .map(|t| t.get())
+        .unwrap_or(1);
+    let rows_per_thread = height.div_ceil(threads).max(1);
+
+    for pass in 0..PASSES {
+        let step = 1 << pass;
+        let sigma_color = SIGMA_COLOR / step as f32;
+
+        let mut filtered = vec![Vec3::ZERO; lighting.len()];
+        std::thread::scope(|scope| {
+            for (band, rows) in filtered.chunks_mut(rows_per_thread * width).enumerate() {
+                let lighting = &lighting;
+                scope.spawn(move || {
+                    for (i, out) in rows.iter_mut().enumerate() {
+                        let p = band * rows_per_thread * width + i;
+                        *out = filter_pixel(
+                            width,
+                            height,
+                            lighting,
+                            guides,
+                            (p % width, p / width),
+                            step,
+                            sigma_color,
+                        );
+                    }
+                });
+            }
+        });
+
+        lighting = filtered;
+    }
+
+    lighting
+        .iter()
+        .zip(guides)
+        .map(|(lighting, guide)| *lighting * albedo(guide))
+        .collect()
+}
+
+/// Albedo the lighting is divided by, black channels are left alone
+fn albedo(guide: &Guide) -> Vec3 {
+    Vec3::select(
+        guide.albedo.cmpgt(Vec3::splat(0.01)),
+        guide.albedo,
+        Vec3::ONE,
+    )
+}
+
+fn filter_pixel(
+    width: usize,
+    height: usize,
+    lighting: &[Vec3],
+    guides: &[Guide],
+    (x, y): (usize, usize),
+    step: usize,
+    sigma_color: f32,
+) -> Vec3 {
+    let center = lighting[y * width + x];
+    let guide = guides[y * width + x];
+    // Fireflies would otherwise stand out of every comparison
+    let compressed = center / (1. + center);
+
+    let mut sum = Vec3::ZERO;
+    let mut total = 0.;
+
+    for (j, ky) in KERNEL.iter().enumerate() {
+        let Some(qy) = (y as isize + (j as isize - 2) * step as isize)
+            .try_into()
+            .ok()
+            .filter(|qy: &usize| *qy < height)
+        else {
+            continue;
+        };
+
+        for (i, kx) in KERNEL.iter().enumerate() {
+            let Some(qx) = (x as isize + (i as isize - 2) * step as isize)
+                .try_into()
+                .ok()
+                .filter(|qx: &usize| *qx < width)
+            else {
+                continue;
+            };
+
+            let sample = lighting[qy * width + qx];
+            let other = guides[qy * width + qx];
+
+            let color_distance = (compressed - sample / (1. + sample)).length_squared();
+            let albedo_distance = (guide.albedo - other.albedo).length_squared();
+            let Some(depth_distance) = depth_distance(guide.depth, other.depth, step) else {
+                continue;
+            };
+            let weight = kx
+                * ky
+                * normal_weight(guide.normal, other.normal)
+                * (-color_distance / (sigma_color * sigma_color)
+                    - albedo_distance / (SIGMA_ALBEDO * SIGMA_ALBEDO)
+                    - depth_distance)
+                    .exp();
+
+            sum += sample * weight;
+            total += weight;
+        }
+    }
+
+    // The center always has some weight, unless the values are not finite
+    if total > 0. {
+        sum / total
+    } else {
+        center
+    }
+}
+
+fn normal_weight(a: Vec3, b: Vec3) -> f32 {
+    match (a == Vec3::ZERO, b == Vec3::ZERO) {
+        // Both see the background
+        (true, true) => 1.,
+        (false, false) => {
+            let mut weight = (a.dot(b) / (a.length() * b.length())).max(0.);
+            for _ in 0..NORMAL_SQUARINGS {
+                weight *= weight;
+            }
+            weight
+        }
+        _ => 0.,
+    }
+}
+
+/// Exponent of the depth weight, `None` when only one of them sees the background
+fn depth_distance(a: f32, b: f32, step: usize) -> Option<f32> {
+    match (a.is_finite(), b.is_finite()) {
+        (false, false) => Some(0.),
+        (true, true) => Some((a - b).abs() / (SIGMA_DEPTH * a.max(1e-3) * step as f32)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::{denoise, Guide};
+
+    const FLAT: Guide = Guide {
+        albedo: Vec3::splat(0.5),
+        normal: Vec3::Z,
+        depth: 1.,
+    };
+
+    /// Same noise in every run
+    fn noise(i: usize) -> f32 {
+        ((i as u32).wrapping_mul(2654435761) >> 8) as f32 / (1 << 24) as f32 - 0.5
+    }
+
+    #[test]
+    fn noise_is_smoothed() {
+        let (width, height) = (32, 32);
+        let color = (0..width * height)
+            .map(|i| Vec3::splat(0.25 + 0.2 * noise(i)))
+            .collect::<Vec<_>>();
+
+        let error = |image: &[Vec3]| {
+            image
+                .iter()
+                .map(|c| (*c - 0.25).length_squared())
+                .sum::<f32>()
+        };
+
+        let denoised = denoise(width, height, &color, &vec![FLAT; width * height]);
+        assert!(error(&denoised) < error(&color) / 10.);
+
+        assert!(denoise(0, 4, &[], &[]).is_empty());
+    }
+
+    #[test]
+    fn edges_are_kept() {
+        let (width, height) = (16, 8);
+        // Left half faces the camera, right half faces up with a brighter texture
+        let guides = (0..width * height)
+            .map(|i| {
+                if i % width < width / 2 {
+                    FLAT
+                } else {
+                    Guide {
+                        albedo: Vec3::splat(0.9),
+                        normal: Vec3::Y,
+                        ..FLAT
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        let color = guides
+            .iter()
+            .enumerate()
+            .map(|(i, g)| g.albedo * (1. + 0.3 * noise(i)))
+            .collect::<Vec<_>>();
+
+        let denoised = denoise(width, height, &color, &guides);
+        for (pixel, guide) in denoised.iter().zip(&guides) {
+            assert!((*pixel - guide.albedo).abs().max_element() < 0.1, "{pixel}");
+        }
+
+        // The background is not mixed with what is in front of it
+        let mut guides = vec![FLAT; 4];
+        guides[3].depth = f32::INFINITY;
+        guides[3].normal = Vec3::ZERO;
+        let color = [Vec3::ONE, Vec3::ONE, Vec3::ONE, Vec3::new(0., 0., 5.)];
+        assert_eq!(denoise(2, 2, &color, &guides)[3], Vec3::new(0., 0., 5.));
+    }
+}
@@ -0,0 +1,359 @@
+use std::{fmt, io::Write, str::FromStr};
+
+use anyhow::bail;
+
+use super::{zlib, Image};
+
+const MAGIC: [u8; 4] = [0x76, 0x2F, 0x31, 0x01];
+/// Scanlines compressed together by the zip compression
+const ZIP_LINES: usize = 16;
+
+/// How the channels are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precision {
+    /// 16 bit floats, plenty for colors and half the size
+    #[default]
+    Half,
+    Float,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    None,
+    /// Deflate of blocks of 16 scanlines, lossless
+    #[default]
+    Zip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Options {
+    pub precision: Precision,
+    pub compression: Compression,
+}
+
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Precision::Half => "half",
+            Precision::Float => "float",
+        })
+    }
+}
+
+impl FromStr for Precision {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> anyhow::Result<Precision> {
+        match name {
+            "half" => Ok(Precision::Half),
+            "float" => Ok(Precision::Float),
+            _ => bail!("unknown exr precision `{name}`, expected half or float"),
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Compression::None => "none",
+            Compression::Zip => "zip",
+        })
+    }
+}
+
+impl FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> anyhow::Result<Compression> {
+        match name {
+            "none" => Ok(Compression::None),
+            "zip" => Ok(Compression::Zip),
+            _ => bail!("unknown exr compression `{name}`, expected none or zip"),
+        }
+    }
+}
+
+/// Single part scanline OpenEXR image with linear R, G and B channels
+pub fn write(writer: &mut impl Write, image: &Image, options: Options) -> anyhow::Result<()> {
+    let lines_per_block = match options.compression {
+        Compression::None => 1,
+        Compression::Zip => ZIP_LINES,
+    };
+
+    let mut header = vec![];
+    header.extend_from_slice(&MAGIC);
+    // Version 2, single part scanline file
+    header.extend_from_slice(&2u32.to_le_bytes());
+
+    let mut channels = vec![];
+    // Channels are sorted by name
+    for name in *b"BGR" {
+        channels.extend_from_slice(&[name, 0]);
+        let pixel_type: u32 = match options.precision {
+            Precision::Half => 1,
+            Precision::Float => 2,
+        };
+        channels.extend_from_slice(&pixel_type.to_le_bytes());
+        // Perceptually linear flag and reserved bytes, then the sampling
+        channels.extend_from_slice(&[0; 4]);
+        channels.extend_from_slice(&1u32.to_le_bytes());
+        channels.extend_from_slice(&1u32.to_le_bytes());
+    }
+    channels.push(0);
+    attribute(&mut header, "channels", "chlist", &channels);
+
+    let compression: u8 = match options.compression {
+        Compression::None => 0,
+        Compression::Zip => 3,
+    };
+    attribute(&mut header, "compression", "compression", &[compression]);
+
+    let window = [0, 0, image.width as i32 - 1, image.height as i32 - 1];
+    let window = window.map(i32::to_le_bytes).concat();
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    // Increasing y
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    let rows = image.pixels.chunks(image.width.max(1)).collect::<Vec<_>>();
+    let blocks =
+        rows.chunks(lines_per_block)
+            .map(|lines| {
+                let mut raw = vec![];
+                for line in lines {
+                    for channel in [2, 1, 0] {
+                        for pixel in line.iter() {
+                            match options.precision {
+                                Precision::Half => raw
+                                    .extend_from_slice(&f32_to_half(pixel[channel]).to_le_bytes()),
+                                Precision::Float => {
+                                    raw.extend_from_slice(&pixel[channel].to_le_bytes())
+                                }
+                            }
+                        }
+                    }
+                }
+
+                match options.compression {
+                    Compression::None => raw,
+                    Compression::Zip => {
+                        let compressed = zlib::compress_fixed(&predict(&raw));
+                        // Blocks as large as the raw data are read as uncompressed
+                        if compressed.len() < raw.len() {
+                            compressed
+                        } else {
+                            raw
+                        }
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+    // Offsets of the blocks from the start of the file follow the header
+    let mut offset = (header.len() + blocks.len() * 8) as u64;
+    for block in &blocks {
+        header.extend_from_slice(&offset.to_le_bytes());
+        offset += 8 + block.len() as u64;
+    }
+    writer.write_all(&header)?;
+
+    for (index, block) in blocks.iter().enumerate() {
+        writer.write_all(&((index * lines_per_block) as i32).to_le_bytes())?;
+        writer.write_all(&(block.len() as u32).to_le_bytes())?;
+        writer.write_all(block)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Splits the even and odd bytes, then stores the difference to the previous byte,
+/// so that the smooth values of an image become runs deflate compresses well
+fn predict(raw: &[u8]) -> Vec<u8> {
+    let mut interleaved = raw.iter().step_by(2).copied().collect::<Vec<_>>();
+    interleaved.extend(raw.iter().skip(1).step_by(2));
+
+    // Backwards, so that the previous byte is still the original one
+    for i in (1..interleaved.len()).rev() {
+        interleaved[i] = interleaved[i]
+            .wrapping_sub(interleaved[i - 1])
+            .wrapping_add(128);
+    }
+
+    interleaved
+}
+
+/// Nearest 16 bit float, rounding ties to even, too large values become infinite
+pub fn f32_to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7F_FFFF;
+
+    if exponent == 0xFF {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7C00 | nan;
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1F {
+        return sign | 0x7C00;
+    }
+
+    let (base, significand, shift) = if exponent <= 0 {
+        // Subnormal, the implicit leading one becomes explicit
+        if exponent < -10 {
+            return sign;
+        }
+        (0, mantissa | 0x80_0000, (14 - exponent) as u32)
+    } else {
+        ((exponent as u32) << 10, mantissa, 13)
+    };
+
+    let half = base | (significand >> shift);
+    let rest = significand & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    // A carry into the exponent is still the right number
+    let half = if rest > halfway || (rest == halfway && half & 1 == 1) {
+        half + 1
+    } else {
+        half
+    };
+
+    sign | half as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::{f32_to_half, predict, write, Compression, Options, Precision};
+    use crate::utils::image::{zlib, Image};
+
+    /// Blocks of the file as their first scanline and data
+    fn blocks(data: &[u8], count: usize) -> Vec<(i32, &[u8])> {
+        let u32_at = |i: usize| u32::from_le_bytes(data[i..i + 4].try_into().unwrap()) as usize;
+
+        // Attributes until an empty name, after the magic number and version
+        let mut i = 8;
+        while data[i] != 0 {
+            i += data[i..].iter().position(|b| *b == 0).unwrap() + 1;
+            i += data[i..].iter().position(|b| *b == 0).unwrap() + 1;
+            i += 4 + u32_at(i);
+        }
+        let table = i + 1;
+
+        (0..count)
+            .map(|block| {
+                let offset = u64::from_le_bytes(data[table + block * 8..][..8].try_into().unwrap());
+                let offset = offset as usize;
+                let y = u32_at(offset) as i32;
+                (y, &data[offset + 8..][..u32_at(offset + 4)])
+            })
+            .collect()
+    }
+
+    fn gradient(width: usize, height: usize) -> Image {
+        Image {
+            width,
+            height,
+            pixels: (0..width * height)
+                .map(|i| Vec3::new((i % width) as f32 / width as f32, 2., 0.))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn half_floats() {
+        assert_eq!(f32_to_half(0.), 0);
+        assert_eq!(f32_to_half(1.), 0x3C00);
+        assert_eq!(f32_to_half(-2.), 0xC000);
+        assert_eq!(f32_to_half(0.1), 0x2E66);
+        assert_eq!(f32_to_half(65504.), 0x7BFF);
+        assert_eq!(f32_to_half(1e6), 0x7C00);
+        assert_eq!(f32_to_half(2f32.powi(-24)), 1);
+        assert_eq!(f32_to_half(2f32.powi(-26)), 0);
+        assert_eq!(f32_to_half(f32::NAN) & 0x7E00, 0x7E00);
+    }
+
+    #[test]
+    fn uncompressed_floats() {
+        let image = Image {
+            width: 2,
+            height: 1,
+            pixels: vec![Vec3::new(1., 2., 3.), Vec3::new(4., 5., 6.)],
+        };
+        let options = Options {
+            precision: Precision::Float,
+            compression: Compression::None,
+        };
+
+        let mut data = vec![];
+        write(&mut data, &image, options).unwrap();
+        assert_eq!(data[..8], [0x76, 0x2F, 0x31, 0x01, 2, 0, 0, 0]);
+
+        let [(y, block)] = blocks(&data, 1)[..] else {
+            panic!("expected a single block");
+        };
+        assert_eq!(y, 0);
+        let values = block
+            .chunks(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        // Every channel of the scanline, in the B, G, R order
+        assert_eq!(values, [3., 6., 2., 5., 1., 4.]);
+    }
+
+    #[test]
+    fn zip_blocks() {
+        let image = gradient(64, 20);
+        let mut raw = vec![];
+        write(
+            &mut raw,
+            &image,
+            Options {
+                compression: Compression::None,
+                ..Options::default()
+            },
+        )
+        .unwrap();
+        let mut zipped = vec![];
+        write(&mut zipped, &image, Options::default()).unwrap();
+
+        assert!(zipped.len() < raw.len() / 4);
+
+        let lines = blocks(&raw, 20);
+        let zipped = blocks(&zipped, 2);
+        assert_eq!(zipped.iter().map(|(y, _)| *y).collect::<Vec<_>>(), [0, 16]);
+
+        let first = lines[..16]
+            .iter()
+            .flat_map(|(_, b)| *b)
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(zlib::decompress(zipped[0].1).unwrap(), predict(&first));
+    }
+}
@@ -0,0 +1,364 @@
+use std::io::Write;
+
+use anyhow::{bail, Context};
+use glam::Vec3;
+
+use super::{Image, MAX_PIXELS};
+
+/// Shortest run worth encoding as such, shorter ones stay among the literal bytes
+const MIN_RUN: usize = 4;
+
+/// Radiance RGBE picture, run length encoded when the width allows it
+pub fn write(writer: &mut impl Write, image: &Image) -> anyhow::Result<()> {
+    write!(
+        writer,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        image.height, image.width
+    )?;
+
+    let run_length_encoded = (8..0x8000).contains(&image.width);
+    let mut out = vec![];
+
+    for row in image.pixels.chunks(image.width.max(1)) {
+        let rgbe = row.iter().map(|p| vec3_to_rgbe(*p)).collect::<Vec<_>>();
+
+        if !run_length_encoded {
+            out.extend(rgbe.iter().flatten());
+            continue;
+        }
+
+        out.extend_from_slice(&[2, 2]);
+        out.extend_from_slice(&(image.width as u16).to_be_bytes());
+        for channel in 0..4 {
+            let bytes = rgbe.iter().map(|p| p[channel]).collect::<Vec<_>>();
+            write_runs(&mut out, &bytes);
+        }
+    }
+
+    writer.write_all(&out)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Shared exponent of the largest channel, negative channels are dropped
+fn vec3_to_rgbe(color: Vec3) -> [u8; 4] {
+    let color = color.clamp(Vec3::ZERO, Vec3::splat(f32::MAX));
+    let max = color.max_element();
+    if max.is_nan() || max <= 1e-32 {
+        return [0; 4];
+    }
+
+    // max = mantissa * 2^exponent with the mantissa in 0.5..1
+    let exponent = (max.log2().floor() as i32 + 1).min(127);
+    let scale = 256. / 2f32.powi(exponent);
+    let [r, g, b] = (color * scale).min(Vec3::splat(255.)).to_array();
+
+    [r as u8, g as u8, b as u8, (exponent + 128) as u8]
+}
+
+/// Encodes a channel of a scanline as runs of a repeated byte and literal bytes
+fn write_runs(out: &mut Vec<u8>, bytes: &[u8]) {
+    let run = |x: usize, limit: usize| {
+        bytes[x..]
+            .iter()
+            .take(limit)
+            .take_while(|b| **b == bytes[x])
+            .count()
+    };
+
+    let mut x = 0;
+    while x < bytes.len() {
+        let length = run(x, 127);
+        if length >= MIN_RUN {
+            out.extend_from_slice(&[128 + length as u8, bytes[x]]);
+            x += length;
+            continue;
+        }
+
+        let start = x;
+        while x < bytes.len() && x - start < 128 && run(x, MIN_RUN) < MIN_RUN {
+            x += 1;
+        }
+        out.push((x - start) as u8);
+        out.extend_from_slice(&bytes[start..x]);
+    }
+}
+
+/// Radiance RGBE picture, run length encoded or flat
+pub fn read(data: &[u8]) -> anyhow::Result<Image> {
+    let mut lines = data.split(|&b| b == b'\n');
+    let mut offset = 0;
+    let mut next_line = || {
+        let line = lines.next()?;
+        offset += line.len() + 1;
+        Some(line)
+    };
+
+    let magic = next_line().unwrap_or_default();
+    if !magic.starts_with(b"#?") {
+        bail!("not a radiance picture, the header must start with `#?`");
+    }
+
+    // Variables until an empty line, only the format matters
+    loop {
+        let line = next_line().context("the header is not terminated by an empty line")?;
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(format) = line.strip_prefix(b"FORMAT=") {
+            if format != b"32-bit_rle_rgbe" {
+                bail!(
+                    "unsupported format `{}`, only 32-bit_rle_rgbe is",
+                    String::from_utf8_lossy(format)
+                );
+            }
+        }
+    }
+
+    let resolution = next_line().context("missing resolution")?;
+    let resolution = String::from_utf8_lossy(resolution);
+    let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (height.parse::<usize>(), width.parse::<usize>()),
+        _ => bail!("unsupported resolution `{resolution}`, only `-Y <height> +X <width>` is"),
+    };
+    let (Ok(height), Ok(width)) = (height, width) else {
+        bail!("invalid resolution `{resolution}`");
+    };
+
+    let mut data = data.get(offset..).unwrap_or_default();
+
+    let pixels = width
+        .checked_mul(height)
+        .filter(|pixels| *pixels <= MAX_PIXELS)
+        .with_context(|| format!("resolution `{resolution}` is too large"))?;
+    // Every scanline takes at least 4 bytes, whatever its encoding
+    if pixels > 0 && height > data.len() / 4 {
+        bail!("unexpected end of file");
+    }
+    let mut rgbe = vec![[0u8; 4]; pixels];
+
+    for (y, row) in rgbe.chunks_mut(width.max(1)).enumerate() {
+        data = read_scanline(data, row).with_context(|| format!("scanline {y}"))?;
+    }
+
+    Ok(Image {
+        width,
+        height,
+        pixels: rgbe.iter().map(|p| rgbe_to_vec3(*p)).collect(),
+    })
+}
+
+fn rgbe_to_vec3([r, g, b, e]: [u8; 4]) -> Vec3 {
+    if e == 0 {
+        return Vec3::ZERO;
+    }
+
+    // The mantissas are fractions of 256
+    let scale = 2f32.powi(e as i32 - 136);
+    Vec3::new(r as f32, g as f32, b as f32) * scale
+}
+
+/// Fills `row`, returns what is left of `data`
+fn read_scanline<'a>(mut data: &'a [u8], row: &mut [[u8; 4]]) -> anyhow::Result<&'a [u8]> {
+    let width = row.len();
+
+    let start = data;
+    let header = take(&mut data, 4)?;
+
+    // New run length encoding, each channel is encoded separately
+    if (8..0x8000).contains(&width) && header[0] == 2 && header[1] == 2 && header[2] & 0x80 == 0 {
+        if (header[2] as usize) << 8 | header[3] as usize != width {
+            bail!("run length encoded width does not match the image");
+        }
+
+        for channel in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let count = take(&mut data, 1)?[0] as usize;
+                let run = count > 128;
+                let count = if run { count - 128 } else { count };
+
+                if count == 0 || x + count > width {
+                    bail!("run length overflows the scanline");
+                }
+
+                if run {
+                    let value = take(&mut data, 1)?[0];
+                    row[x..x + count]
+                        .iter_mut()
+                        .for_each(|p| p[channel] = value);
+                } else {
+                    for (p, value) in row[x..x + count].iter_mut().zip(take(&mut data, count)?) {
+                        p[channel] = *value;
+                    }
+                }
+                x += count;
+            }
+        }
+
+        return Ok(data);
+    }
+
+    // Flat pixels, possibly with the old run length encoding repeating the previous one
+    data = start;
+    let mut x = 0;
+    let mut shift = 0;
+    while x < width {
+        let pixel: [u8; 4] = take(&mut data, 4)?.try_into().expect("4 bytes were taken");
+
+        if pixel[..3] == [1, 1, 1] {
+            if x == 0 {
+                bail!("repeat marker without a previous pixel");
+            }
+            // The count of every marker is 8 bits above the one of the previous marker
+            if shift >= 24 {
+                bail!("too many repeat markers in a row");
+            }
+
+            let count = (pixel[3] as usize) << shift;
+            if x + count > width {
+                bail!("run length overflows the scanline");
+            }
+
+            let previous = row[x - 1];
+            row[x..x + count].fill(previous);
+            x += count;
+            shift += 8;
+        } else {
+            row[x] = pixel;
+            x += 1;
+            shift = 0;
+        }
+    }
+
+    Ok(data)
+}
+
+fn take<'a>(data: &mut &'a [u8], count: usize) -> anyhow::Result<&'a [u8]> {
+    if data.len() < count {
+        bail!("unexpected end of file");
+    }
+
+    let (taken, rest) = data.split_at(count);
+    *data = rest;
+    Ok(taken)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::{read, vec3_to_rgbe, write};
+    use crate::utils::image::Image;
+
+    fn header(width: usize, height: usize) -> Vec<u8> {
+        format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\nEXPOSURE=1\n\n-Y {height} +X {width}\n")
+            .into_bytes()
+    }
+
+    #[test]
+    fn flat_pixels() {
+        let mut data = header(2, 1);
+        // 128 * 2^(129 - 136) = 1, and 0.5 * 2 = 1 with a smaller mantissa
+        data.extend([128, 64, 0, 129, 64, 64, 64, 130]);
+
+        let image = read(&data).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(
+            image.pixels,
+            vec![Vec3::new(1., 0.5, 0.), Vec3::new(1., 1., 1.)]
+        );
+    }
+
+    #[test]
+    fn run_length_encoded() {
+        let mut data = header(8, 1);
+        data.extend([2, 2, 0, 8]);
+        // Red: a run of 8, green: 8 literal values, blue: two runs, exponent: a run
+        data.extend([128 + 8, 128]);
+        data.extend([8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        data.extend([128 + 4, 0, 128 + 4, 128]);
+        data.extend([128 + 8, 129]);
+
+        let image = read(&data).unwrap();
+        assert_eq!(image.pixels[0], Vec3::new(1., 0., 0.));
+        assert_eq!(image.pixels[7], Vec3::new(1., 0.875, 1.));
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let pixels: Vec<Vec3> = (0..40)
+            .map(|i| match i % 10 {
+                0..6 => Vec3::splat(0.25),
+                _ => Vec3::new(i as f32 * 10., 1., 0.),
+            })
+            .collect();
+        for width in [20, 4] {
+            let image = Image {
+                width,
+                height: 40 / width,
+                pixels: pixels.clone(),
+            };
+
+            let mut data = vec![];
+            write(&mut data, &image).unwrap();
+            let read = read(&data).unwrap();
+
+            assert_eq!((read.width, read.height), (image.width, image.height));
+            for (a, b) in read.pixels.iter().zip(&image.pixels) {
+                // 8 bits of mantissa shared by the channels
+                assert!((*a - *b).abs().max_element() <= b.max_element() / 128.);
+            }
+        }
+
+        assert_eq!(vec3_to_rgbe(Vec3::new(1., 0.5, -1.)), [128, 64, 0, 129]);
+        assert_eq!(vec3_to_rgbe(Vec3::NAN), [0; 4]);
+        assert_eq!(vec3_to_rgbe(Vec3::INFINITY), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn invalid_pictures() {
+        let error = |data: &[u8]| format!("{:#}", read(data).err().unwrap());
+
+        assert_eq!(
+            error(b"P6\n"),
+            "not a radiance picture, the header must start with `#?`"
+        );
+        assert_eq!(
+            error(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n"),
+            "unsupported format `32-bit_rle_xyze`, only 32-bit_rle_rgbe is"
+        );
+        assert_eq!(
+            error(b"#?RADIANCE\n\n+Y 1 +X 1\n"),
+            "unsupported resolution `+Y 1 +X 1`, only `-Y <height> +X <width>` is"
+        );
+        assert_eq!(
+            error(&[header(2, 1), vec![1, 2, 3, 4]].concat()),
+            "scanline 0: unexpected end of file"
+        );
+        assert_eq!(
+            error(&header(100000, 100000)),
+            "resolution `-Y 100000 +X 100000` is too large"
+        );
+        assert_eq!(
+            error(&header(usize::MAX, 2)),
+            format!("resolution `-Y 2 +X {}` is too large", usize::MAX)
+        );
+        assert_eq!(error(&header(4, 1000)), "unexpected end of file");
+
+        // Empty repeat markers only shift the count of the next ones
+        let markers = [
+            [9, 9, 9, 128],
+            [1, 1, 1, 0],
+            [1, 1, 1, 0],
+            [1, 1, 1, 0],
+            [1, 1, 1, 0],
+        ];
+        assert_eq!(
+            error(&[header(8, 1), markers.concat()].concat()),
+            "scanline 0: too many repeat markers in a row"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use anyhow::{bail, Context};
+use glam::Vec3;
+
+use super::{colors::Color, tone_mapping::ToneMapping};
+
+pub mod exr;
+pub mod hdr;
+pub mod png;
+pub mod ppm;
+pub mod zlib;
+
+/// Largest image read, 16384 x 16384, so that a corrupted header cannot exhaust the memory
+const MAX_PIXELS: usize = 1 << 28;
+
+/// Decoded image, rows go from top to bottom and channels are linear, 1 is white
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3>,
+}
+
+impl Image {
+    pub fn pixel(&self, x: usize, y: usize) -> Vec3 {
+        self.pixels[y * self.width + x]
+    }
+}
+
+/// Splits a packed `0x00RRGGBB` color into its channels
+pub fn color_to_rgb(color: Color) -> [u8; 3] {
+    [(color >> 16) as u8, (color >> 8) as u8, color as u8]
+}
+
+/// How [`write`] stores an image
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct WriteOptions {
+    /// Only for the 8 bit formats, high dynamic range ones keep the linear values
+    pub tone_mapping: ToneMapping,
+    pub exr: exr::Options,
+}
+
+/// Writes the image to `path`, the format is chosen by the file extension
+pub fn write(path: &Path, image: &Image, options: &WriteOptions) -> anyhow::Result<()> {
+    let (width, height) = (image.width, image.height);
+    if image.pixels.len() != width * height {
+        bail!(
+            "expected {} pixels for a {width}x{height} image, got {}",
+            width * height,
+            image.pixels.len()
+        );
+    }
+
+    let extension = extension(path);
+
+    let file = File::create(path).with_context(|| format!("cannot create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    let colors = || {
+        image
+            .pixels
+            .iter()
+            .map(|p| options.tone_mapping.color(*p))
+            .collect::<Vec<_>>()
+    };
+
+    match extension.as_deref() {
+        Some("png") => png::write(&mut writer, width as u32, height as u32, &colors())?,
+        Some("ppm") => ppm::write(&mut writer, width as u32, height as u32, &colors())?,
+        Some("exr") => exr::write(&mut writer, image, options.exr)?,
+        Some("hdr") => hdr::write(&mut writer, image)?,
+        _ => bail!(
+            "unsupported image format for {}, use .png, .ppm, .exr or .hdr",
+            path.display()
+        ),
+    }
+
+    Ok(())
+}
+
+/// Whether [`write`] keeps the linear values at `path`, instead of tone mapping them
+pub fn is_high_dynamic_range(path: &Path) -> bool {
+    matches!(extension(path).as_deref(), Some("exr" | "hdr"))
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+}
+
+/// Reads the image at `path`, the format is chosen by the file extension
+pub fn read(path: &Path) -> anyhow::Result<Image> {
+    let extension = extension(path);
+
+    let decode = match extension.as_deref() {
+        Some("png") => png::read,
+        Some("ppm" | "pgm" | "pnm") => ppm::read,
+        Some("hdr") => hdr::read,
+        _ => bail!(
+            "unsupported image format for {}, use .png, .ppm or .hdr",
+            path.display()
+        ),
+    };
+
+    let data = std::fs::read(path).with_context(|| format!("cannot read {}", path.display()))?;
+    decode(&data).with_context(|| format!("invalid image {}", path.display()))
+}
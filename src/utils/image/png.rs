@@ -0,0 +1,390 @@
+use std::io::Write;
+
+use anyhow::{bail, Context};
+use glam::Vec3;
+
+use crate::utils::colors::{srgb_to_linear, Color};
+
+use super::{color_to_rgb, zlib, Image, MAX_PIXELS};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+
+    !crc
+}
+
+fn write_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> anyhow::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+
+    let mut checked = Vec::with_capacity(data.len() + 4);
+    checked.extend_from_slice(kind);
+    checked.extend_from_slice(data);
+    writer.write_all(&crc32(&checked).to_be_bytes())?;
+
+    Ok(())
+}
+
+/// 8 bit RGB png, without filtering nor compression
+pub fn write(
+    writer: &mut impl Write,
+    width: u32,
+    height: u32,
+    pixels: &[Color],
+) -> anyhow::Result<()> {
+    writer.write_all(&SIGNATURE)?;
+
+    let mut header = vec![];
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // bit depth 8, color type 2 (rgb), default compression, filter and no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    write_chunk(writer, b"IHDR", &header)?;
+
+    let mut raw = Vec::with_capacity((width as usize * 3 + 1) * height as usize);
+    for row in pixels.chunks(width as usize) {
+        // Filter type None
+        raw.push(0);
+        raw.extend(row.iter().flat_map(|p| color_to_rgb(*p)));
+    }
+    write_chunk(writer, b"IDAT", &zlib::compress(&raw))?;
+
+    write_chunk(writer, b"IEND", &[])?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Decodes every non interlaced png, the alpha channel is dropped
+pub fn read(data: &[u8]) -> anyhow::Result<Image> {
+    let Some(mut data) = data.strip_prefix(&SIGNATURE) else {
+        bail!("not a png, the signature does not match");
+    };
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut compressed = vec![];
+
+    loop {
+        let (kind, chunk, rest) = read_chunk(data)?;
+        data = rest;
+
+        match kind {
+            b"IHDR" => header = Some(Header::parse(chunk)?),
+            b"PLTE" => palette = chunk,
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            // Ancillary chunks like gamma or text do not change the pixels
+            _ => {}
+        }
+    }
+
+    let header = header.context("missing IHDR chunk")?;
+    let raw = zlib::decompress(&compressed).context("IDAT")?;
+    let rows = unfilter(&header, &raw)?;
+
+    let max = ((1u32 << header.depth) - 1) as f32;
+    let mut pixels = Vec::with_capacity(header.width * header.height);
+
+    for row in rows.chunks(header.stride()) {
+        for x in 0..header.width {
+            let sample = |channel: usize| header.sample(row, x * header.channels() + channel);
+
+            let color = match header.color_type {
+                // Gray, with alpha
+                0 | 4 => Vec3::splat(sample(0) as f32 / max),
+                // Rgb, with alpha
+                2 | 6 => Vec3::new(sample(0) as f32, sample(1) as f32, sample(2) as f32) / max,
+                _ => {
+                    let index = sample(0) as usize * 3;
+                    let rgb = palette
+                        .get(index..index + 3)
+                        .context("palette index out of the palette")?;
+                    Vec3::new(rgb[0] as f32, rgb[1] as f32, rgb[2] as f32) / 255.
+                }
+            };
+
+            pixels.push(color.map(srgb_to_linear));
+        }
+    }
+
+    Ok(Image {
+        width: header.width,
+        height: header.height,
+        pixels,
+    })
+}
+
+fn read_chunk(data: &[u8]) -> anyhow::Result<(&[u8], &[u8], &[u8])> {
+    if data.len() < 12 {
+        bail!("unexpected end of file");
+    }
+
+    let len = u32::from_be_bytes(data[..4].try_into().expect("4 bytes")) as usize;
+    let checked = data.get(4..8 + len).context("unexpected end of file")?;
+    let crc = data
+        .get(8 + len..12 + len)
+        .context("unexpected end of file")?;
+
+    let kind = &checked[..4];
+    if crc32(checked).to_be_bytes() != crc {
+        bail!("corrupted {} chunk", String::from_utf8_lossy(kind));
+    }
+
+    Ok((kind, &checked[4..], &data[12 + len..]))
+}
+
+struct Header {
+    width: usize,
+    height: usize,
+    depth: u8,
+    color_type: u8,
+}
+
+impl Header {
+    fn parse(chunk: &[u8]) -> anyhow::Result<Header> {
+        if chunk.len() != 13 {
+            bail!("invalid IHDR chunk");
+        }
+
+        let header = Header {
+            width: u32::from_be_bytes(chunk[..4].try_into().expect("4 bytes")) as usize,
+            height: u32::from_be_bytes(chunk[4..8].try_into().expect("4 bytes")) as usize,
+            depth: chunk[8],
+            color_type: chunk[9],
+        };
+
+        let depths: &[u8] = match header.color_type {
+            0 => &[1, 2, 4, 8, 16],
+            3 => &[1, 2, 4, 8],
+            2 | 4 | 6 => &[8, 16],
+            other => bail!("invalid color type {other}"),
+        };
+        if !depths.contains(&header.depth) {
+            bail!(
+                "invalid bit depth {} for color type {}",
+                header.depth,
+                header.color_type
+            );
+        }
+        if chunk[12] != 0 {
+            bail!("interlaced images are not supported");
+        }
+
+        // Checked once here, the sizes of the rows and pixels are then known to fit
+        if header.width == 0 || header.height == 0 {
+            bail!("invalid size {} x {}", header.width, header.height);
+        }
+        let bytes = header
+            .width
+            .checked_mul(header.channels() * header.depth as usize)
+            .map(|bits| bits.div_ceil(8) + 1)
+            .and_then(|row| row.checked_mul(header.height));
+        let pixels = header.width.checked_mul(header.height);
+        if bytes.is_none() || pixels.is_none_or(|pixels| pixels > MAX_PIXELS) {
+            bail!("image of {} x {} is too large", header.width, header.height);
+        }
+
+        Ok(header)
+    }
+
+    fn channels(&self) -> usize {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    /// Bytes of a row, without the filter type
+    fn stride(&self) -> usize {
+        (self.width * self.channels() * self.depth as usize).div_ceil(8)
+    }
+
+    /// Distance to the corresponding byte of the previous pixel, for filtering
+    fn pixel_bytes(&self) -> usize {
+        (self.channels() * self.depth as usize).div_ceil(8)
+    }
+
+    /// Sample number `index` of an unfiltered row
+    fn sample(&self, row: &[u8], index: usize) -> u32 {
+        match self.depth {
+            16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]) as u32,
+            8 => row[index] as u32,
+            depth => {
+                // Packed from the most significant bit
+                let bit = index * depth as usize;
+                let shift = 8 - depth as usize - bit % 8;
+                (row[bit / 8] >> shift) as u32 & ((1 << depth) - 1)
+            }
+        }
+    }
+}
+
+/// Reverts the per row filters, returns the rows without the filter types
+fn unfilter(header: &Header, raw: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let stride = header.stride();
+    let left = header.pixel_bytes();
+
+    if raw.len() < (stride + 1) * header.height {
+        bail!("not enough image data");
+    }
+
+    let mut rows = vec![0u8; stride * header.height];
+    for (y, filtered) in raw.chunks(stride + 1).take(header.height).enumerate() {
+        let (previous, current) = rows.split_at_mut(y * stride);
+        let above = (y > 0).then(|| &previous[(y - 1) * stride..]);
+        let current = &mut current[..stride];
+
+        for i in 0..stride {
+            let a = if i >= left { current[i - left] } else { 0 } as i16;
+            let b = above.map_or(0, |row| row[i]) as i16;
+            let c = match above {
+                Some(row) if i >= left => row[i - left],
+                _ => 0,
+            } as i16;
+
+            let predicted = match filtered[0] {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => (a + b) / 2,
+                4 => {
+                    let p = a + b - c;
+                    let (pa, pb, pc) = ((p - a).abs(), (p - b).abs(), (p - c).abs());
+                    if pa <= pb && pa <= pc {
+                        a
+                    } else if pb <= pc {
+                        b
+                    } else {
+                        c
+                    }
+                }
+                other => bail!("invalid filter type {other} on row {y}"),
+            };
+
+            current[i] = filtered[i + 1].wrapping_add(predicted as u8);
+        }
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::{crc32, read, write};
+    use crate::utils::colors::srgb_to_linear;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn srgb(r: u8, g: u8, b: u8) -> Vec3 {
+        Vec3::new(r as f32, g as f32, b as f32).map(|c| srgb_to_linear(c / 255.))
+    }
+
+    #[test]
+    fn crc32_known_value() {
+        assert_eq!(crc32(b"IEND"), 0xAE426082);
+    }
+
+    #[test]
+    fn writes_signature_header_and_end() {
+        let mut out = vec![];
+        write(&mut out, 1, 1, &[0xFFFFFF]).unwrap();
+
+        assert_eq!(&out[..8], &super::SIGNATURE);
+        assert_eq!(&out[12..16], b"IHDR");
+        assert_eq!(&out[out.len() - 8..out.len() - 4], b"IEND");
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut out = vec![];
+        write(&mut out, 2, 1, &[0xFF0000, 0x00FF80]).unwrap();
+
+        let image = read(&out).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixels, vec![srgb(255, 0, 0), srgb(0, 255, 128)]);
+    }
+
+    #[test]
+    fn every_filter_type() {
+        // 3x5 rgb, row `y` is filtered with type `y`
+        let image = read(&hex(concat!(
+            "89504e470d0a1a0a0000000d49484452000000030000000508020000000f13c1f50000002f4944415478",
+            "da636060f8ef2672bd47633da35cd45737911b40c42417f50d82986db6e4186d7f0e442c200111100200",
+            "e4ca14730dc8378a0000000049454e44ae426082"
+        )))
+        .unwrap();
+
+        assert_eq!(image.pixel(2, 0), srgb(140, 40, 175));
+        assert_eq!(image.pixel(1, 1), srgb(100, 110, 205));
+        assert_eq!(image.pixel(2, 2), srgb(200, 220, 155));
+        assert_eq!(image.pixel(0, 3), srgb(90, 14, 225));
+        assert_eq!(image.pixel(2, 4), srgb(4, 144, 135));
+    }
+
+    #[test]
+    fn packed_palette() {
+        // 3x1, 2 bits per pixel
+        let image = read(&hex(concat!(
+            "89504e470d0a1a0a0000000d4948445200000003000000010203000000668efc270000000c504c5445ff",
+            "000000ff000000fffffffffb0060f60000000a4944415478da63b8010000da00d9bdc57e150000000049",
+            "454e44ae426082"
+        )))
+        .unwrap();
+
+        assert_eq!(
+            image.pixels,
+            vec![Vec3::ONE, Vec3::new(0., 1., 0.), Vec3::new(0., 0., 1.)]
+        );
+    }
+
+    #[test]
+    fn corrupted_pngs() {
+        let error = |data: &[u8]| format!("{:#}", read(data).err().unwrap());
+
+        assert_eq!(error(b"GIF89a"), "not a png, the signature does not match");
+
+        let mut out = vec![];
+        write(&mut out, 1, 1, &[0]).unwrap();
+        out[20] ^= 1;
+        assert_eq!(error(&out), "corrupted IHDR chunk");
+
+        // Valid checksums around sizes that do not fit
+        let header = |width: u32, height: u32| {
+            let mut out = vec![];
+            write(&mut out, 1, 1, &[0]).unwrap();
+            out[16..20].copy_from_slice(&width.to_be_bytes());
+            out[20..24].copy_from_slice(&height.to_be_bytes());
+            // 16 bits rgba
+            out[24..26].copy_from_slice(&[16, 6]);
+            let crc = crc32(&out[12..29]);
+            out[29..33].copy_from_slice(&crc.to_be_bytes());
+            error(&out)
+        };
+        assert_eq!(
+            header(u32::MAX, u32::MAX),
+            "image of 4294967295 x 4294967295 is too large"
+        );
+        assert_eq!(header(0, 1), "invalid size 0 x 1");
+    }
+}
@@ -0,0 +1,178 @@
+use std::io::Write;
+
+use anyhow::{bail, Context};
+use glam::Vec3;
+
+use crate::utils::colors::{srgb_to_linear, Color};
+
+use super::{color_to_rgb, Image};
+
+/// Binary (P6) portable pixmap
+pub fn write(
+    writer: &mut impl Write,
+    width: u32,
+    height: u32,
+    pixels: &[Color],
+) -> anyhow::Result<()> {
+    write!(writer, "P6\n{width} {height}\n255\n")?;
+
+    let data = pixels
+        .iter()
+        .flat_map(|p| color_to_rgb(*p))
+        .collect::<Vec<_>>();
+
+    writer.write_all(&data)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Portable pixmap or graymap, plain (P2, P3) or binary (P5, P6)
+pub fn read(data: &[u8]) -> anyhow::Result<Image> {
+    let mut position = 0;
+
+    // Whitespace separated, `#` starts a comment until the end of the line
+    let mut token = || -> anyhow::Result<&str> {
+        loop {
+            match data.get(position) {
+                Some(b'#') => {
+                    while data.get(position).is_some_and(|&b| b != b'\n') {
+                        position += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => position += 1,
+                _ => break,
+            }
+        }
+
+        let start = position;
+        while data.get(position).is_some_and(|b| !b.is_ascii_whitespace()) {
+            position += 1;
+        }
+
+        if start == position {
+            bail!("unexpected end of file");
+        }
+        std::str::from_utf8(&data[start..position]).context("invalid header")
+    };
+
+    let magic = token()?;
+    let (channels, binary) = match magic {
+        "P2" => (1, false),
+        "P3" => (3, false),
+        "P5" => (1, true),
+        "P6" => (3, true),
+        _ => bail!("not a pnm image, unknown magic number `{magic}`"),
+    };
+
+    let mut number = |name: &str| -> anyhow::Result<usize> {
+        let value = token()?;
+        value
+            .parse()
+            .with_context(|| format!("invalid {name} `{value}`"))
+    };
+
+    let width = number("width")?;
+    let height = number("height")?;
+    let max = number("maximum value")?;
+    if !(1..=65535).contains(&max) {
+        bail!("the maximum value must be between 1 and 65535, found {max}");
+    }
+
+    let count = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .with_context(|| format!("image of {width} x {height} is too large"))?;
+    let samples: Vec<usize> = if binary {
+        // A single whitespace separates the header from the samples
+        let start = position + 1;
+        let size = if max > 255 { 2 } else { 1 };
+        let bytes = data
+            .get(start..)
+            .and_then(|bytes| bytes.get(..count.checked_mul(size)?))
+            .context("unexpected end of file")?;
+
+        if size == 2 {
+            bytes
+                .chunks(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+                .collect()
+        } else {
+            bytes.iter().map(|&b| b as usize).collect()
+        }
+    } else {
+        (0..count)
+            .map(|_| number("sample"))
+            .collect::<anyhow::Result<_>>()?
+    };
+
+    let pixels = samples
+        .chunks(channels)
+        .map(|p| {
+            let color = match *p {
+                [gray] => Vec3::splat(gray as f32),
+                [r, g, b, ..] => Vec3::new(r as f32, g as f32, b as f32),
+                _ => unreachable!("pixels have 1 or 3 samples"),
+            };
+            (color / max as f32).min(Vec3::ONE).map(srgb_to_linear)
+        })
+        .collect();
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::{read, write};
+
+    #[test]
+    fn header_and_pixels() {
+        let mut out = vec![];
+        write(&mut out, 2, 1, &[0xFF0012, 0x00AA00]).unwrap();
+
+        assert_eq!(&out[..11], b"P6\n2 1\n255\n");
+        assert_eq!(&out[11..], &[0xFF, 0x00, 0x12, 0x00, 0xAA, 0x00]);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let mut out = vec![];
+        write(&mut out, 2, 1, &[0xFF0000, 0x0000FF]).unwrap();
+
+        let image = read(&out).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(
+            image.pixels,
+            vec![Vec3::new(1., 0., 0.), Vec3::new(0., 0., 1.)]
+        );
+    }
+
+    #[test]
+    fn plain_with_comments() {
+        let image = read(b"P2\n# a comment\n2 1 # another\n4\n0 4\n").unwrap();
+        assert_eq!(image.pixels, vec![Vec3::ZERO, Vec3::ONE]);
+
+        let error = |data: &[u8]| format!("{:#}", read(data).err().unwrap());
+        assert_eq!(error(b"P7\n"), "not a pnm image, unknown magic number `P7`");
+        assert_eq!(error(b"P3 1 1 255 0 0"), "unexpected end of file");
+        assert_eq!(
+            error(b"P3 1 x 255"),
+            "invalid height `x`: invalid digit found in string"
+        );
+        let huge = format!("P6 {} 2 255\n", usize::MAX / 2);
+        assert_eq!(
+            error(huge.as_bytes()),
+            format!("image of {} x 2 is too large", usize::MAX / 2)
+        );
+        assert_eq!(
+            error(b"P6 4611686018427387904 1 65535\n"),
+            "unexpected end of file"
+        );
+    }
+}
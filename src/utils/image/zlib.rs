@@ -0,0 +1,561 @@
+use anyhow::{bail, Context};
+
+/// Largest payload of a single stored deflate block
+const MAX_STORED_BLOCK: usize = u16::MAX as usize;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Distances a match can reach back to
+const WINDOW: usize = 1 << 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// Candidates looked at for each match, longer chains compress a bit better but slower
+const MAX_CHAIN: usize = 32;
+const HASH_BITS: u32 = 15;
+
+/// Order the code length code lengths of a dynamic block are stored in
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let mut a = 1u32;
+    let mut b = 0u32;
+
+    // 5552 is the largest n such that the sums cannot overflow a u32
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+
+    (b << 16) | a
+}
+
+/// Wraps `data` in a zlib stream made of stored (uncompressed) deflate blocks.
+///
+/// Every zlib reader accepts it, and it is fast enough to not matter next
+/// to the time spent rendering the image.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut out = Vec::with_capacity(data.len() + blocks * 5 + 6);
+
+    // CM = 8 (deflate), CINFO = 7 (32K window), no dictionary, fastest level
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+
+    out
+}
+
+/// Wraps `data` in a zlib stream made of a single fixed huffman block.
+///
+/// Repetitions are found through a hash chain of the last 3 bytes, which is
+/// enough for the long runs of similar bytes in pixel data.
+pub fn compress_fixed(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+
+    // Last block, fixed huffman codes
+    writer.bits(1, 1);
+    writer.bits(1, 2);
+
+    let mut chain = HashChain::new(data);
+
+    let mut i = 0;
+    while i < data.len() {
+        let (mut length, mut distance) = (0, 0);
+
+        if i + MIN_MATCH <= data.len() {
+            let mut candidate = chain.head[chain.hash(i)];
+            for _ in 0..MAX_CHAIN {
+                if candidate == usize::MAX || i - candidate >= WINDOW {
+                    break;
+                }
+
+                let matching = data[candidate..]
+                    .iter()
+                    .zip(&data[i..])
+                    .take(MAX_MATCH)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if matching > length {
+                    (length, distance) = (matching, i - candidate);
+                    if length == MAX_MATCH {
+                        break;
+                    }
+                }
+
+                candidate = chain.previous[candidate % WINDOW];
+            }
+        }
+
+        if length >= MIN_MATCH {
+            let index = LENGTH_BASE
+                .iter()
+                .rposition(|base| *base as usize <= length)
+                .expect("lengths start at 3");
+            writer.fixed_symbol(257 + index);
+            writer.bits(
+                (length - LENGTH_BASE[index] as usize) as u32,
+                LENGTH_EXTRA[index] as u32,
+            );
+
+            let index = DISTANCE_BASE
+                .iter()
+                .rposition(|base| *base as usize <= distance)
+                .expect("distances start at 1");
+            writer.code(index as u32, 5);
+            writer.bits(
+                (distance - DISTANCE_BASE[index] as usize) as u32,
+                DISTANCE_EXTRA[index] as u32,
+            );
+
+            (i..i + length).for_each(|i| chain.insert(i));
+            i += length;
+        } else {
+            writer.fixed_symbol(data[i] as usize);
+            chain.insert(i);
+            i += 1;
+        }
+    }
+    writer.fixed_symbol(256);
+
+    // CM = 8 (deflate), CINFO = 7 (32K window), no dictionary, default level
+    let mut out = vec![0x78, 0x9C];
+    out.extend(writer.finish());
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+
+    out
+}
+
+/// Positions of the data, linked by the hash of the 3 bytes starting there
+struct HashChain<'a> {
+    data: &'a [u8],
+    /// Latest position of every hash, `usize::MAX` if none
+    head: Vec<usize>,
+    /// Previous position with the same hash, indexed modulo the window
+    previous: Vec<usize>,
+}
+
+impl<'a> HashChain<'a> {
+    fn new(data: &'a [u8]) -> HashChain<'a> {
+        HashChain {
+            data,
+            head: vec![usize::MAX; 1 << HASH_BITS],
+            previous: vec![usize::MAX; WINDOW],
+        }
+    }
+
+    fn hash(&self, i: usize) -> usize {
+        let key = u32::from_le_bytes([self.data[i], self.data[i + 1], self.data[i + 2], 0]);
+        (key.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, i: usize) {
+        if i + MIN_MATCH <= self.data.len() {
+            let hash = self.hash(i);
+            self.previous[i % WINDOW] = self.head[hash];
+            self.head[hash] = i;
+        }
+    }
+}
+
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, value: u32, count: u32) {
+        self.buffer |= (value as u64) << self.count;
+        self.count += count;
+
+        while self.count >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Huffman codes are packed starting from their most significant bit
+    fn code(&mut self, code: u32, length: u32) {
+        self.bits(code.reverse_bits() >> (32 - length), length);
+    }
+
+    fn fixed_symbol(&mut self, symbol: usize) {
+        let (code, length) = match symbol {
+            0..=143 => (0x30 + symbol, 8),
+            144..=255 => (0x190 + symbol - 144, 9),
+            256..=279 => (symbol - 256, 7),
+            _ => (0xC0 + symbol - 280, 8),
+        };
+        self.code(code as u32, length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.buffer as u8);
+        }
+        self.out
+    }
+}
+
+/// Deflate bits are packed starting from the least significant bit of each byte
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> anyhow::Result<u32> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = *self
+                .data
+                .get(self.position / 8)
+                .context("unexpected end of the compressed data")?;
+            value |= ((byte >> (self.position % 8)) as u32 & 1) << i;
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+
+    /// Skips to the start of the next byte, and returns the `len` bytes from there
+    fn bytes(&mut self, len: usize) -> anyhow::Result<&[u8]> {
+        let start = self.position.div_ceil(8);
+        let bytes = self
+            .data
+            .get(start..start + len)
+            .context("unexpected end of the compressed data")?;
+        self.position = (start + len) * 8;
+
+        Ok(bytes)
+    }
+}
+
+/// Canonical huffman code, decoded one bit at a time
+struct Huffman {
+    /// Number of codes of each length
+    counts: [u16; 16],
+    /// Symbols ordered by code
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> anyhow::Result<Huffman> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Over subscribed codes cannot be decoded, incomplete ones are allowed
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                bail!("invalid huffman code lengths");
+            }
+        }
+
+        let mut symbols = Vec::with_capacity(lengths.len());
+        for len in 1..16 {
+            symbols.extend(
+                (0..lengths.len() as u16).filter(|&symbol| lengths[symbol as usize] == len),
+            );
+        }
+
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> anyhow::Result<u16> {
+        // First code and index of the symbols of the current length
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        bail!("invalid huffman code")
+    }
+}
+
+/// Inflates a zlib stream, checking its header and checksum
+pub fn decompress(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let [cmf, flags, ..] = *data else {
+        bail!("missing zlib header");
+    };
+    if cmf & 0x0F != 8 || !((cmf as u16) << 8 | flags as u16).is_multiple_of(31) {
+        bail!("invalid zlib header");
+    }
+    if flags & 0x20 != 0 {
+        bail!("preset zlib dictionaries are not supported");
+    }
+
+    let mut reader = BitReader {
+        data: &data[2..],
+        position: 0,
+    };
+    let out = inflate(&mut reader)?;
+
+    let checksum = reader.bytes(4).context("missing zlib checksum")?;
+    if u32::from_be_bytes(checksum.try_into().expect("4 bytes were read")) != adler32(&out) {
+        bail!("zlib checksum mismatch");
+    }
+
+    Ok(out)
+}
+
+/// Raw deflate decoder, for stored, fixed and dynamic huffman blocks
+fn inflate(reader: &mut BitReader) -> anyhow::Result<Vec<u8>> {
+    let mut out = vec![];
+
+    loop {
+        let last = reader.bits(1)? == 1;
+
+        match reader.bits(2)? {
+            0 => {
+                let header = reader.bytes(4)?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                if len != !u16::from_le_bytes([header[2], header[3]]) {
+                    bail!("corrupted stored block length");
+                }
+                out.extend_from_slice(reader.bytes(len as usize)?);
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+
+                let literals = Huffman::new(&lengths)?;
+                let distances = Huffman::new(&[5; 30])?;
+                inflate_block(reader, &mut out, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(reader)?;
+                inflate_block(reader, &mut out, &literals, &distances)?;
+            }
+            _ => bail!("invalid deflate block type"),
+        }
+
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+fn read_dynamic_codes(reader: &mut BitReader) -> anyhow::Result<(Huffman, Huffman)> {
+    let literals = reader.bits(5)? as usize + 257;
+    let distances = reader.bits(5)? as usize + 1;
+    let code_lengths = reader.bits(4)? as usize + 4;
+
+    let mut lengths = [0u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_lengths] {
+        lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&lengths)?;
+
+    let mut lengths = Vec::with_capacity(literals + distances);
+    while lengths.len() < literals + distances {
+        let (value, repeat) = match code_lengths.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .context("repeated length without a previous one")?;
+                (previous, 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+
+    if lengths.len() > literals + distances {
+        bail!("code lengths overflow the dynamic block header");
+    }
+    if lengths[256] == 0 {
+        bail!("missing end of block code");
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literals])?,
+        Huffman::new(&lengths[literals..])?,
+    ))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> anyhow::Result<()> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                if index >= LENGTH_BASE.len() {
+                    bail!("invalid length code");
+                }
+                let len =
+                    LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+
+                let index = distances.decode(reader)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    bail!("invalid distance code");
+                }
+                let distance = DISTANCE_BASE[index] as usize
+                    + reader.bits(DISTANCE_EXTRA[index] as u32)? as usize;
+
+                if distance > out.len() {
+                    bail!("distance goes back before the start of the data");
+                }
+
+                // The copy can overlap with what it produces, byte by byte it is
+                let start = out.len() - distance;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{adler32, compress, compress_fixed, decompress};
+
+    #[test]
+    fn adler32_known_value() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+    }
+
+    #[test]
+    fn stored_blocks_are_split() {
+        let data = vec![7u8; 70_000];
+        let out = compress(&data);
+
+        // header + 2 block headers + data + checksum
+        assert_eq!(out.len(), 2 + 2 * 5 + data.len() + 4);
+        assert_eq!(out[2], 0);
+        assert_eq!(out[2 + 5 + 65535], 1);
+    }
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn stored_round_trip() {
+        let data: Vec<u8> = (0..70_000).map(|i| (i * 7 % 251) as u8).collect();
+        assert_eq!(decompress(&compress(&data)).unwrap(), data);
+        assert_eq!(decompress(&compress(&[])).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn fixed_huffman_round_trip() {
+        let data: Vec<u8> = (0..100_000).map(|i| (i / 300 % 7 * 30) as u8).collect();
+        let out = compress_fixed(&data);
+        assert!(out.len() < data.len() / 20);
+        assert_eq!(decompress(&out).unwrap(), data);
+
+        // Nothing to repeat, and matches reaching the whole window
+        let noise: Vec<u8> = (0..70_000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+        let data = [noise.as_slice(), noise.as_slice()].concat();
+        assert_eq!(decompress(&compress_fixed(&data)).unwrap(), data);
+        assert_eq!(decompress(&compress_fixed(&[])).unwrap(), Vec::<u8>::new());
+        assert_eq!(decompress(&compress_fixed(b"ab")).unwrap(), b"ab");
+    }
+
+    #[test]
+    fn fixed_huffman() {
+        let data = hex("78dacb48cdc9c957c8402701680308b1");
+        assert_eq!(decompress(&data).unwrap(), b"hello hello hello hello");
+    }
+
+    #[test]
+    fn dynamic_huffman() {
+        // Skewed letters, so that zlib picks a dynamic block
+        let mut x = 1u32;
+        let expected: Vec<u8> = (0..400)
+            .map(|_| {
+                x = x.wrapping_mul(1103515245).wrapping_add(12345) % (1 << 31);
+                b"aaaaaaaabbbbccde"[((x >> 16) % 16) as usize]
+            })
+            .collect();
+
+        let data = hex(concat!(
+            "78da4590d111043108426b05b4ff160ed4ecf9911885e718144882805a02d0a81c0ed7e5567aa0b613a9",
+            "73a29aec9e92c48aa844d53afb990c0802427399d1b99b51332734ae36478dd500c5643454d5c35fc032",
+            "70bc17ec2ad76d635d3f846c007ece09bd34c988adb15bc3ce24f1e4597185f3aed6ee8495dc12bb56e7",
+            "3957a6f601feaa586fb4bf0eb32e1f99dbf4cfff00f0fc98f9"
+        ));
+        assert_eq!(decompress(&data).unwrap(), expected);
+    }
+
+    #[test]
+    fn corrupted_streams() {
+        let error = |data: &[u8]| format!("{:#}", decompress(data).err().unwrap());
+
+        assert_eq!(error(&[0x78]), "missing zlib header");
+        assert_eq!(error(&[0x78, 0x02]), "invalid zlib header");
+
+        let mut data = hex("78dacb48cdc9c957c8402701680308b1");
+        *data.last_mut().unwrap() ^= 1;
+        assert_eq!(error(&data), "zlib checksum mismatch");
+        assert_eq!(error(&data[..8]), "unexpected end of the compressed data");
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use glam::Vec3;
+
+use crate::utils::ray::Ray;
+
+use super::{reflect, refract, Material, Scatter};
+
+/// Clear material that refracts, like glass or water
+#[derive(Debug)]
+pub struct Dielectric {
+    /// Relative to the medium outside of the surface, 1.5 for glass
+    refraction_index: f32,
+}
+
+impl Dielectric {
+    pub fn new(refraction_index: f32) -> Arc<Self> {
+        Arc::new(Self { refraction_index })
+    }
+}
+
+/// Schlick's approximation of the Fresnel reflectance
+pub fn reflectance(cosine: f32, refraction_ratio: f32) -> f32 {
+    let r0 = ((1. - refraction_ratio) / (1. + refraction_ratio)).powi(2);
+    r0 + (1. - r0) * (1. - cosine).powi(5)
+}
+
+impl Material for Dielectric {
+    fn scatter(
+        &self,
+        ray: &crate::utils::ray::Ray,
+        hit: &crate::utils::meshes::Hit,
+    ) -> Option<Scatter> {
+        // Entering the surface from outside, or leaving it
+        let ratio = if hit.front_face {
+            1. / self.refraction_index
+        } else {
+            self.refraction_index
+        };
+
+        let direction = ray.direction.normalize();
+
+        let cos_theta = (-direction).dot(hit.normal).min(1.);
+        let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+
+        // Snell's law has no solution, total internal reflection
+        let cannot_refract = ratio * sin_theta > 1.;
+
+        let scattered = if cannot_refract || reflectance(cos_theta, ratio) > rand::random::<f32>() {
+            reflect(direction, hit.normal)
+        } else {
+            refract(direction, hit.normal, ratio)
+        };
+
+        Some(Scatter {
+            ray: Ray::new(hit.point, scattered).with_time(ray.time),
+            attenuation: Vec3::ONE,
+            pdf: None,
+        })
+    }
+
+    fn albedo(&self, _hit: &crate::utils::meshes::Hit) -> Vec3 {
+        // Clear, nothing is absorbed
+        Vec3::ONE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use glam::Vec3;
+
+    use super::{reflectance, Dielectric};
+    use crate::utils::{materials::Material, meshes::Hit, ray::Ray};
+
+    fn hit(front_face: bool) -> Hit {
+        Hit {
+            distance: 1.,
+            point: Vec3::ZERO,
+            normal: Vec3::Y,
+            front_face,
+            u: 0.,
+            v: 0.,
+            material: Dielectric::new(1.5) as Arc<dyn Material>,
+        }
+    }
+
+    #[test]
+    fn schlick_limits() {
+        // Glass reflects 4% at normal incidence, everything at grazing angles
+        assert!((reflectance(1., 1. / 1.5) - 0.04).abs() < 1e-6);
+        assert!((reflectance(0., 1. / 1.5) - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn total_internal_reflection() {
+        let glass = Dielectric::new(1.5);
+
+        // Leaving the glass at a grazing angle, past the critical angle
+        let ray = Ray::new(Vec3::new(-1., -0.2, 0.), Vec3::new(1., 0.2, 0.));
+        for _ in 0..100 {
+            let scatter = glass.scatter(&ray, &hit(false)).unwrap();
+            assert!(scatter.ray.direction.y < 0.);
+            assert_eq!(scatter.attenuation, Vec3::ONE);
+        }
+    }
+
+    #[test]
+    fn refraction_bends_towards_the_normal() {
+        let glass = Dielectric::new(1.5);
+        let ray = Ray::new(Vec3::new(-1., 1., 0.), Vec3::new(1., -1., 0.));
+
+        let refracted = (0..100)
+            .map(|_| glass.scatter(&ray, &hit(true)).unwrap().ray.direction)
+            .find(|d| d.y < 0.)
+            .expect("most rays are refracted at 45 degrees");
+
+        // sin(45°) / 1.5
+        let sin = refracted.normalize().x;
+        assert!((sin - std::f32::consts::FRAC_1_SQRT_2 / 1.5).abs() < 1e-5);
+    }
+}
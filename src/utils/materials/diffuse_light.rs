@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use crate::utils::colors::ColorVec;
+
+use super::{Material, Scatter};
+
+/// Emits light evenly from both sides of the surface and reflects nothing
+#[derive(Debug)]
+pub struct DiffuseLight(ColorVec);
+
+impl DiffuseLight {
+    /// `color` is in the 0 to 255 scale, `intensity` scales it to light up whole rooms
+    pub fn new(color: ColorVec, intensity: f32) -> Arc<Self> {
+        Arc::new(Self(color * intensity))
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _ray: &crate::utils::ray::Ray,
+        _hit: &crate::utils::meshes::Hit,
+    ) -> Option<Scatter> {
+        None
+    }
+
+    fn emitted(&self, _ray: &crate::utils::ray::Ray, _hit: &crate::utils::meshes::Hit) -> ColorVec {
+        self.0
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+}
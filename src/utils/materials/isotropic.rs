@@ -0,0 +1,53 @@
+use std::{f32::consts::PI, sync::Arc};
+
+use glam::Vec3;
+
+use crate::utils::{
+    colors::{self, ColorVec},
+    meshes::Hit,
+    random_unit_vector,
+    ray::Ray,
+    textures::{solid::SolidColor, Texture},
+};
+
+use super::{Material, Scatter};
+
+/// Phase function of fog and smoke, light is scattered equally in every direction
+#[derive(Debug)]
+pub struct Isotropic(Arc<dyn Texture>);
+
+impl Isotropic {
+    pub fn new(color: ColorVec) -> Arc<Self> {
+        Self::textured(SolidColor::new(color))
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>) -> Arc<Self> {
+        Arc::new(Self(albedo))
+    }
+}
+
+/// Density of a direction picked uniformly on the sphere
+const UNIFORM_PDF: f32 = 1. / (4. * PI);
+
+impl Material for Isotropic {
+    fn scatter(&self, ray: &Ray, hit: &Hit) -> Option<Scatter> {
+        Some(Scatter {
+            ray: Ray::new(hit.point, random_unit_vector()).with_time(ray.time),
+            attenuation: self.albedo(hit),
+            pdf: Some(UNIFORM_PDF),
+        })
+    }
+
+    /// There is no surface, so no cosine either
+    fn eval(&self, _ray: &Ray, hit: &Hit, _direction: Vec3) -> ColorVec {
+        self.albedo(hit) * UNIFORM_PDF
+    }
+
+    fn pdf(&self, _ray: &Ray, _hit: &Hit, _direction: Vec3) -> f32 {
+        UNIFORM_PDF
+    }
+
+    fn albedo(&self, hit: &Hit) -> Vec3 {
+        colors::vec3_to_scalar(&self.0.value(hit.u, hit.v, hit.point))
+    }
+}
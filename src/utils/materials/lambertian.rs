@@ -1,37 +1,71 @@
-use std::sync::Arc;
+use std::{f32::consts::PI, sync::Arc};
+
+use glam::Vec3;
 
 use crate::utils::{
     colors::{self, ColorVec},
+    meshes::Hit,
     random_unit_vector,
     ray::Ray,
+    textures::{solid::SolidColor, Texture},
 };
 
-use super::Material;
+use super::{Material, Scatter};
 
 #[derive(Debug)]
-pub struct Lambertian(ColorVec);
+pub struct Lambertian(Arc<dyn Texture>);
 
 impl Lambertian {
     pub fn new(color: ColorVec) -> Arc<Self> {
-        Arc::new(Self(colors::vec3_to_scalar(&color)))
+        Self::textured(SolidColor::new(color))
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>) -> Arc<Self> {
+        Arc::new(Self(albedo))
     }
 }
 
 impl Material for Lambertian {
     fn scatter(
         &self,
-        _ray: &crate::utils::ray::Ray,
+        ray: &crate::utils::ray::Ray,
         hit: &crate::utils::meshes::Hit,
-    ) -> (crate::utils::ray::Ray, colors::ColorVec) {
+    ) -> Option<Scatter> {
+        // Cosine weighted, which cancels out with the cosine in the rendering equation
         let mut direction = hit.normal + random_unit_vector();
 
         if direction.abs().element_sum() < 1e-7 {
             direction = hit.normal;
         }
 
-        let scattered = Ray::new(hit.point, direction);
-        let attenuation = self.0;
+        let direction = direction.normalize();
+
+        Some(Scatter {
+            ray: Ray::new(hit.point, direction).with_time(ray.time),
+            attenuation: self.albedo(hit),
+            pdf: Some(direction.dot(hit.normal).max(0.) / PI),
+        })
+    }
+
+    fn eval(
+        &self,
+        _ray: &crate::utils::ray::Ray,
+        hit: &crate::utils::meshes::Hit,
+        direction: Vec3,
+    ) -> ColorVec {
+        self.albedo(hit) / PI * direction.normalize().dot(hit.normal).max(0.)
+    }
+
+    fn pdf(
+        &self,
+        _ray: &crate::utils::ray::Ray,
+        hit: &crate::utils::meshes::Hit,
+        direction: Vec3,
+    ) -> f32 {
+        direction.normalize().dot(hit.normal).max(0.) / PI
+    }
 
-        (scattered, attenuation)
+    fn albedo(&self, hit: &Hit) -> Vec3 {
+        colors::vec3_to_scalar(&self.0.value(hit.u, hit.v, hit.point))
     }
 }
@@ -2,17 +2,35 @@ use std::sync::Arc;
 
 use crate::utils::{
     colors::{self, ColorVec},
+    random_unit_vector,
     ray::Ray,
+    textures::{solid::SolidColor, Texture},
 };
 
-use super::Material;
+use super::{reflect, Material, Scatter};
 
 #[derive(Debug)]
-pub struct Metal(ColorVec);
+pub struct Metal {
+    albedo: Arc<dyn Texture>,
+    /// Radius of the sphere the reflection is perturbed within, 0 is a perfect mirror
+    fuzz: f32,
+}
 
 impl Metal {
     pub fn new(color: ColorVec) -> Arc<Self> {
-        Arc::new(Self(colors::vec3_to_scalar(&color)))
+        Self::fuzzy(color, 0.)
+    }
+
+    /// Rough metal, `fuzz` goes from 0 (mirror) to 1 (very rough)
+    pub fn fuzzy(color: ColorVec, fuzz: f32) -> Arc<Self> {
+        Self::textured(SolidColor::new(color), fuzz)
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>, fuzz: f32) -> Arc<Self> {
+        Arc::new(Self {
+            albedo,
+            fuzz: fuzz.clamp(0., 1.),
+        })
     }
 }
 
@@ -21,12 +39,84 @@ impl Material for Metal {
         &self,
         ray: &crate::utils::ray::Ray,
         hit: &crate::utils::meshes::Hit,
-    ) -> (crate::utils::ray::Ray, colors::ColorVec) {
-        let reflected = ray.direction - 2. * ray.direction.dot(hit.normal) * hit.normal;
+    ) -> Option<Scatter> {
+        let mut reflected = reflect(ray.direction, hit.normal);
+
+        if self.fuzz > 0. {
+            reflected = reflected.normalize() + self.fuzz * random_unit_vector();
+        }
+
+        // Perturbed below the surface, the ray is absorbed
+        if reflected.dot(hit.normal) <= 0. {
+            return None;
+        }
+
+        Some(Scatter {
+            ray: Ray::new(hit.point, reflected).with_time(ray.time),
+            attenuation: colors::vec3_to_scalar(&self.albedo.value(hit.u, hit.v, hit.point)),
+            pdf: None,
+        })
+    }
+
+    fn albedo(&self, hit: &crate::utils::meshes::Hit) -> ColorVec {
+        colors::vec3_to_scalar(&self.albedo.value(hit.u, hit.v, hit.point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use glam::Vec3;
+
+    use super::Metal;
+    use crate::utils::{colors::WHITE, materials::Material, meshes::Hit, ray::Ray};
+
+    fn hit() -> Hit {
+        Hit {
+            distance: 1.,
+            point: Vec3::ZERO,
+            normal: Vec3::Y,
+            front_face: true,
+            u: 0.,
+            v: 0.,
+            material: Metal::new(WHITE) as Arc<dyn Material>,
+        }
+    }
+
+    #[test]
+    fn mirror_reflection() {
+        let ray = Ray::new(Vec3::new(-1., 1., 0.), Vec3::new(1., -1., 0.));
+        let scatter = Metal::new(WHITE).scatter(&ray, &hit()).unwrap();
+
+        assert_eq!(scatter.ray.direction, Vec3::new(1., 1., 0.));
+        assert_eq!(scatter.attenuation, Vec3::ONE);
+    }
+
+    #[test]
+    fn fuzzy_reflection_stays_close() {
+        let ray = Ray::new(Vec3::new(-1., 1., 0.), Vec3::new(1., -1., 0.));
+        let metal = Metal::fuzzy(WHITE, 0.3);
+
+        for _ in 0..100 {
+            let Some(scatter) = metal.scatter(&ray, &hit()) else {
+                continue;
+            };
+            let mirror = Vec3::new(1., 1., 0.).normalize();
+            assert!((scatter.ray.direction - mirror).length() <= 0.3 + 1e-5);
+        }
+    }
+
+    #[test]
+    fn grazing_rays_below_the_surface_are_absorbed() {
+        let ray = Ray::new(Vec3::new(-1., 0.01, 0.), Vec3::new(1., -0.01, 0.));
+        let metal = Metal::fuzzy(WHITE, 1.);
 
-        let scattered = Ray::new(hit.point, reflected);
-        let attenuation = self.0;
+        let scattered = (0..1000)
+            .filter_map(|_| metal.scatter(&ray, &hit()))
+            .inspect(|scatter| assert!(scatter.ray.direction.y > 0.))
+            .count();
 
-        (scattered, attenuation)
+        assert!(scattered < 1000);
     }
 }
@@ -1,10 +1,72 @@
 use std::fmt::Debug;
 
+use glam::Vec3;
+
 use super::{colors, meshes::Hit, ray::Ray};
 
+pub mod dielectric;
+pub mod diffuse_light;
+pub mod isotropic;
 pub mod lambertian;
 pub mod metal;
+pub mod principled;
+
+/// Ray leaving a surface, and how much of the light it brings back is kept
+#[derive(Debug)]
+pub struct Scatter {
+    pub ray: Ray,
+    pub attenuation: colors::ColorVec,
+    /// Density the direction was picked with, see [`Material::pdf`].
+    /// `None` for specular bounces, which lights cannot be sampled for
+    pub pdf: Option<f32>,
+}
 
 pub trait Material: Debug + Send + Sync {
-    fn scatter(&self, ray: &Ray, hit: &Hit) -> (Ray, colors::ColorVec);
+    /// `None` when the ray is absorbed, which terminates the path
+    fn scatter(&self, ray: &Ray, hit: &Hit) -> Option<Scatter>;
+
+    /// Light emitted by the surface, in the same 0 to 255 scale as the colors
+    fn emitted(&self, _ray: &Ray, _hit: &Hit) -> colors::ColorVec {
+        Vec3::ZERO
+    }
+
+    /// Fraction of the light reflected at the hit, from 0 to 1, regardless of the directions.
+    /// Shown by the albedo output variable
+    fn albedo(&self, _hit: &Hit) -> Vec3 {
+        Vec3::ZERO
+    }
+
+    /// Whether [`Material::emitted`] can be non zero, meshes made of it are sampled as lights
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// BSDF times the cosine with the normal, for light coming from `direction` and
+    /// leaving towards the origin of `ray`. Only needed when `scatter` reports a pdf
+    fn eval(&self, _ray: &Ray, _hit: &Hit, _direction: Vec3) -> colors::ColorVec {
+        Vec3::ZERO
+    }
+
+    /// Density, in solid angle, of [`Material::scatter`] picking `direction`
+    fn pdf(&self, _ray: &Ray, _hit: &Hit, _direction: Vec3) -> f32 {
+        0.
+    }
+}
+
+/// Mirrors `direction` around the surface with the given `normal`
+pub fn reflect(direction: Vec3, normal: Vec3) -> Vec3 {
+    direction - 2. * direction.dot(normal) * normal
+}
+
+/// Bends the unit vector `direction` through a surface, following Snell's law.
+///
+/// `ratio` is the refraction index of the medium being left over the one being
+/// entered, total internal reflection must be handled by the caller.
+pub fn refract(direction: Vec3, normal: Vec3, ratio: f32) -> Vec3 {
+    let cos_theta = (-direction).dot(normal).min(1.);
+
+    let perpendicular = ratio * (direction + cos_theta * normal);
+    let parallel = -(1. - perpendicular.length_squared()).abs().sqrt() * normal;
+
+    perpendicular + parallel
 }
@@ -0,0 +1,460 @@
+use std::{f32::consts::PI, sync::Arc};
+
+use glam::Vec3;
+
+use crate::utils::{
+    colors::{self, ColorVec},
+    meshes::Hit,
+    random_unit_vector,
+    ray::Ray,
+    textures::{solid::SolidColor, Texture},
+};
+
+use super::{dielectric::reflectance, reflect, refract, Material, Scatter};
+
+/// Roughness of the clearcoat, a thin glossy varnish
+const CLEARCOAT_ROUGHNESS: f32 = 0.1;
+
+/// Physically based material after the Disney principled BSDF.
+///
+/// A diffuse base with sheen, a GGX specular layer, a clearcoat on top and rough
+/// transmission are mixed by the [`PrincipledParams`]. The defaults are a plain
+/// rough plastic.
+#[derive(Debug, Clone)]
+pub struct Principled {
+    base_color: Arc<dyn Texture>,
+    metallic: f32,
+    roughness: f32,
+    specular: f32,
+    clearcoat: f32,
+    sheen: f32,
+    transmission: f32,
+    ior: f32,
+}
+
+/// Parameters of a [`Principled`] material, from 0 to 1 but the index of refraction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrincipledParams {
+    /// Conductor instead of dielectric, reflections are tinted by the base color
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Reflectance of dielectrics seen head on, 0.5 is the 4% of most materials
+    pub specular: f32,
+    /// Strength of a glossy varnish layer on top
+    pub clearcoat: f32,
+    /// Soft white rim at grazing angles, for cloth
+    pub sheen: f32,
+    /// Light going through the surface instead of being diffused, like glass
+    pub transmission: f32,
+    /// Index of refraction of the transmitted light
+    pub ior: f32,
+}
+
+impl Default for PrincipledParams {
+    fn default() -> Self {
+        Self {
+            metallic: 0.,
+            roughness: 0.5,
+            specular: 0.5,
+            clearcoat: 0.,
+            sheen: 0.,
+            transmission: 0.,
+            ior: 1.5,
+        }
+    }
+}
+
+impl Principled {
+    pub fn new(color: ColorVec) -> Arc<Self> {
+        Self::textured(SolidColor::new(color))
+    }
+
+    pub fn textured(base_color: Arc<dyn Texture>) -> Arc<Self> {
+        Self::with_params(base_color, PrincipledParams::default())
+    }
+
+    pub fn with_params(base_color: Arc<dyn Texture>, params: PrincipledParams) -> Arc<Self> {
+        let PrincipledParams {
+            metallic,
+            roughness,
+            specular,
+            clearcoat,
+            sheen,
+            transmission,
+            ior,
+        } = params;
+
+        Arc::new(Self {
+            base_color,
+            metallic,
+            roughness,
+            specular,
+            clearcoat,
+            sheen,
+            transmission,
+            ior,
+        })
+    }
+
+    fn base_color(&self, hit: &Hit) -> ColorVec {
+        colors::vec3_to_scalar(&self.base_color.value(hit.u, hit.v, hit.point))
+    }
+
+    /// Weights of the diffuse, specular, clearcoat and transmission lobes.
+    /// Lobes are also sampled proportionally to them
+    fn lobes(&self) -> [f32; 4] {
+        let dielectric = 1. - self.metallic;
+        let transmission = dielectric * self.transmission;
+
+        [
+            dielectric * (1. - self.transmission),
+            // Transmission reflects its own share of the light
+            1. - transmission,
+            0.25 * self.clearcoat,
+            transmission,
+        ]
+    }
+
+    /// BSDF times the cosine of every lobe but the transmission, which is not sampled
+    /// towards lights
+    fn reflection(&self, hit: &Hit, outgoing: Vec3, incoming: Vec3) -> ColorVec {
+        let n = hit.normal;
+        let (cos_o, cos_i) = (n.dot(outgoing), n.dot(incoming));
+        if cos_o <= 0. || cos_i <= 0. {
+            return Vec3::ZERO;
+        }
+
+        let [diffuse, specular, clearcoat, _] = self.lobes();
+        let base = self.base_color(hit);
+        let half = (outgoing + incoming).normalize();
+        let cos_d = incoming.dot(half);
+
+        let microfacet = |roughness: f32, fresnel: Vec3| {
+            let alpha = alpha(roughness);
+            fresnel * ggx(n, half, alpha) * smith(cos_o, alpha) * smith(cos_i, alpha)
+                / (4. * cos_o * cos_i)
+        };
+
+        let f0 = Vec3::splat(0.08 * self.specular).lerp(base, self.metallic);
+        let sheen = (1. - self.metallic) * (1. - self.transmission) * self.sheen;
+
+        let f = diffuse * base / PI
+            + Vec3::splat(sheen * schlick_weight(cos_d))
+            + specular * microfacet(self.roughness, schlick(f0, cos_d))
+            + clearcoat * microfacet(CLEARCOAT_ROUGHNESS, schlick(Vec3::splat(0.04), cos_d));
+
+        f * cos_i
+    }
+
+    /// Density of picking `incoming` with any lobe but the transmission
+    fn reflection_pdf(&self, hit: &Hit, outgoing: Vec3, incoming: Vec3) -> f32 {
+        let n = hit.normal;
+        let cos_i = n.dot(incoming);
+        if n.dot(outgoing) <= 0. || cos_i <= 0. {
+            return 0.;
+        }
+
+        let lobes = self.lobes();
+        let total: f32 = lobes.iter().sum();
+        let [diffuse, specular, clearcoat, _] = lobes.map(|w| w / total);
+
+        let half = (outgoing + incoming).normalize();
+        let cos_h = n.dot(half);
+        // Density of the half vector, over the jacobian of the reflection
+        let microfacet =
+            |roughness: f32| ggx(n, half, alpha(roughness)) * cos_h / (4. * outgoing.dot(half));
+
+        diffuse * cos_i / PI
+            + specular * microfacet(self.roughness)
+            + clearcoat * microfacet(CLEARCOAT_ROUGHNESS)
+    }
+
+    /// Rough glass, the microfacet normal is sampled and the ray reflected or refracted
+    /// through it following the Fresnel equations. Returns the direction and its weight
+    fn transmit(&self, hit: &Hit, outgoing: Vec3) -> Option<(Vec3, ColorVec)> {
+        let n = hit.normal;
+        let alpha = alpha(self.roughness);
+        let m = sample_ggx(n, alpha);
+
+        let cos = outgoing.dot(m);
+        if cos <= 0. {
+            return None;
+        }
+
+        let ratio = if hit.front_face {
+            1. / self.ior
+        } else {
+            self.ior
+        };
+        let cannot_refract = ratio * (1. - cos * cos).max(0.).sqrt() > 1.;
+
+        let refracted = !cannot_refract && reflectance(cos, ratio) <= rand::random::<f32>();
+        let (incoming, tint) = if refracted {
+            (refract(-outgoing, m, ratio), self.base_color(hit))
+        } else {
+            (reflect(-outgoing, m), Vec3::ONE)
+        };
+
+        // Reflections must stay above the surface and refractions go below it
+        let cos_i = n.dot(incoming);
+        if cos_i == 0. || (cos_i < 0.) != refracted {
+            return None;
+        }
+
+        let cos_o = n.dot(outgoing);
+        let weight = smith(cos_o, alpha) * smith(cos_i.abs(), alpha) * cos / (cos_o * n.dot(m));
+
+        Some((incoming, tint * weight))
+    }
+}
+
+/// From the perceptual roughness to the width of the GGX distribution
+fn alpha(roughness: f32) -> f32 {
+    (roughness * roughness).max(1e-3)
+}
+
+/// GGX distribution of the microfacet normals `m` around `n`
+fn ggx(n: Vec3, m: Vec3, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    // `1 - cos² (1 - a2)`, without the cancellation of narrow lobes close to the peak
+    let d = n.dot(m).powi(2) * a2 + n.cross(m).length_squared();
+    a2 / (PI * d * d)
+}
+
+/// Smith masking for GGX, the fraction of microfacets visible from a direction
+fn smith(cos: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    2. * cos / (cos + (a2 + (1. - a2) * cos * cos).sqrt())
+}
+
+fn schlick_weight(cos: f32) -> f32 {
+    (1. - cos).clamp(0., 1.).powi(5)
+}
+
+fn schlick(f0: Vec3, cos: f32) -> Vec3 {
+    f0 + (Vec3::ONE - f0) * schlick_weight(cos)
+}
+
+/// Microfacet normal around `n`, with density `ggx(cos) * cos`
+fn sample_ggx(n: Vec3, alpha: f32) -> Vec3 {
+    let (u1, u2) = (rand::random::<f32>(), rand::random::<f32>());
+
+    let cos2 = (1. - u1) / (1. + (alpha * alpha - 1.) * u1);
+    let (cos, sin) = (cos2.sqrt(), (1. - cos2).max(0.).sqrt());
+    let phi = 2. * PI * u2;
+
+    let (t, b) = n.any_orthonormal_pair();
+    ((t * phi.cos() + b * phi.sin()) * sin + n * cos).normalize()
+}
+
+impl Material for Principled {
+    fn scatter(&self, ray: &Ray, hit: &Hit) -> Option<Scatter> {
+        let outgoing = -ray.direction.normalize();
+        let n = hit.normal;
+
+        let [diffuse, specular, _, transmission] = self.lobes();
+        let total: f32 = self.lobes().iter().sum();
+        let pick = rand::random::<f32>() * total;
+
+        if pick < transmission {
+            let (incoming, weight) = self.transmit(hit, outgoing)?;
+
+            return Some(Scatter {
+                ray: Ray::new(hit.point, incoming).with_time(ray.time),
+                // Picked with a chance of `transmission / total`
+                attenuation: weight * total,
+                pdf: None,
+            });
+        }
+
+        let incoming = if pick < transmission + diffuse {
+            (n + random_unit_vector()).normalize_or(n)
+        } else if pick < transmission + diffuse + specular {
+            reflect(-outgoing, sample_ggx(n, alpha(self.roughness)))
+        } else {
+            reflect(-outgoing, sample_ggx(n, alpha(CLEARCOAT_ROUGHNESS)))
+        };
+
+        let incoming = incoming.normalize();
+        let pdf = self.reflection_pdf(hit, outgoing, incoming);
+        if pdf <= 0. {
+            return None;
+        }
+
+        Some(Scatter {
+            ray: Ray::new(hit.point, incoming).with_time(ray.time),
+            attenuation: self.reflection(hit, outgoing, incoming) / pdf,
+            pdf: Some(pdf),
+        })
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit, direction: Vec3) -> ColorVec {
+        self.reflection(hit, -ray.direction.normalize(), direction.normalize())
+    }
+
+    fn pdf(&self, ray: &Ray, hit: &Hit, direction: Vec3) -> f32 {
+        self.reflection_pdf(hit, -ray.direction.normalize(), direction.normalize())
+    }
+
+    fn albedo(&self, hit: &Hit) -> Vec3 {
+        self.base_color(hit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use glam::Vec3;
+
+    use super::{Principled, PrincipledParams};
+    use crate::utils::{
+        colors::{ColorVec, WHITE},
+        meshes::Hit,
+        random_unit_vector,
+        ray::Ray,
+        textures::solid::SolidColor,
+    };
+
+    fn principled(color: ColorVec, params: PrincipledParams) -> Arc<Principled> {
+        Principled::with_params(SolidColor::new(color), params)
+    }
+
+    fn hit(material: Arc<Principled>) -> Hit {
+        Hit {
+            distance: 1.,
+            point: Vec3::ZERO,
+            normal: Vec3::Y,
+            front_face: true,
+            u: 0.,
+            v: 0.,
+            material,
+        }
+    }
+
+    fn incoming() -> Ray {
+        Ray::new(Vec3::new(-1., 1., 0.), Vec3::new(1., -1., 0.))
+    }
+
+    #[test]
+    fn attenuation_is_the_bsdf_over_the_pdf() {
+        let hit = hit(principled(
+            Vec3::new(200., 100., 50.),
+            PrincipledParams {
+                metallic: 0.3,
+                roughness: 0.4,
+                clearcoat: 1.,
+                sheen: 0.5,
+                ..Default::default()
+            },
+        ));
+        let ray = incoming();
+
+        for _ in 0..100 {
+            let Some(scatter) = hit.material.scatter(&ray, &hit) else {
+                continue;
+            };
+            let direction = scatter.ray.direction;
+            let pdf = hit.material.pdf(&ray, &hit, direction);
+
+            // The clearcoat is sharp enough for rounding errors to show
+            assert!((scatter.pdf.unwrap() - pdf).abs() <= 1e-3 * pdf);
+            let expected = hit.material.eval(&ray, &hit, direction) / pdf;
+            assert!(scatter.attenuation.abs_diff_eq(expected, 1e-3));
+        }
+    }
+
+    #[test]
+    fn pdf_integrates_to_the_scattered_fraction() {
+        for material in [
+            Principled::new(WHITE),
+            principled(
+                WHITE,
+                PrincipledParams {
+                    metallic: 1.,
+                    roughness: 0.9,
+                    ..Default::default()
+                },
+            ),
+            principled(
+                WHITE,
+                PrincipledParams {
+                    metallic: 0.5,
+                    roughness: 0.8,
+                    ..Default::default()
+                },
+            ),
+        ] {
+            let hit = hit(material);
+            let ray = Ray::new(Vec3::new(0., 1., -0.2), Vec3::new(0., -1., 0.2));
+            let count = 100_000;
+
+            // Uniform directions on the sphere have a density of 1 / 4π
+            let integral = (0..count)
+                .map(|_| hit.material.pdf(&ray, &hit, random_unit_vector()))
+                .sum::<f32>()
+                * 4.
+                * std::f32::consts::PI
+                / count as f32;
+
+            // Rough reflections going below the surface are absorbed, and missing from the pdf
+            let scattered = (0..count)
+                .filter(|_| hit.material.scatter(&ray, &hit).is_some())
+                .count() as f32
+                / count as f32;
+
+            assert!(
+                (integral - scattered).abs() < 0.02,
+                "{integral} {scattered}"
+            );
+        }
+    }
+
+    #[test]
+    fn white_metal_keeps_most_of_the_energy() {
+        let hit = hit(principled(
+            WHITE,
+            PrincipledParams {
+                metallic: 1.,
+                roughness: 0.3,
+                ..Default::default()
+            },
+        ));
+        let ray = incoming();
+
+        let count = 20_000;
+        let kept = (0..count)
+            .filter_map(|_| hit.material.scatter(&ray, &hit))
+            .map(|scatter| scatter.attenuation.x)
+            .sum::<f32>()
+            / count as f32;
+
+        assert!((0.85..1.01).contains(&kept), "{kept}");
+    }
+
+    #[test]
+    fn smooth_glass_lets_light_through() {
+        let hit = hit(principled(
+            WHITE,
+            PrincipledParams {
+                transmission: 1.,
+                roughness: 0.,
+                ..Default::default()
+            },
+        ));
+        let ray = Ray::new(Vec3::Y, Vec3::NEG_Y);
+
+        let count = 10_000;
+        let through = (0..count)
+            .filter_map(|_| hit.material.scatter(&ray, &hit))
+            .filter(|scatter| {
+                assert!(scatter.pdf.is_none());
+                scatter.ray.direction.y < 0.
+            })
+            .count();
+
+        // Glass reflects 4% of the light seen head on, and a bit of specular on top
+        let through = through as f32 / count as f32;
+        assert!((0.9..0.97).contains(&through), "{through}");
+    }
+}
@@ -0,0 +1,419 @@
+use std::sync::Arc;
+
+use glam::Vec3;
+
+use crate::utils::{aabb::Aabb, ray::Ray};
+
+use super::{Hit, Mesh};
+
+/// Amount of buckets the centroids are sorted in when evaluating the SAH
+const BINS: usize = 16;
+/// Leaves bigger than this are always split, even when the SAH says otherwise
+const MAX_LEAF_SIZE: usize = 8;
+/// Cost of visiting a node, relative to intersecting a primitive
+const TRAVERSAL_COST: f32 = 0.5;
+/// Past this depth nodes are split at the median, which bounds the traversal stack
+const MAX_SAH_DEPTH: usize = 64;
+const STACK_SIZE: usize = 128;
+
+#[derive(Debug, Clone)]
+struct Node {
+    bounds: Aabb,
+    /// First primitive for leaves, right child for interior nodes (the left one is next)
+    offset: u32,
+    /// Amount of primitives, 0 for interior nodes
+    count: u32,
+    axis: u8,
+}
+
+/// Bounding volume hierarchy over anything with a bounding box.
+///
+/// The tree only stores indices, so it can be shared by [`Bvh`] and by meshes
+/// that keep their primitives in their own buffers, like triangle meshes.
+#[derive(Debug, Clone, Default)]
+pub struct BvhTree {
+    nodes: Vec<Node>,
+    indices: Vec<u32>,
+}
+
+impl BvhTree {
+    /// Builds the tree with the surface area heuristic, `bounds` must all be finite
+    pub fn build(bounds: &[Aabb]) -> BvhTree {
+        let mut tree = BvhTree {
+            nodes: Vec::with_capacity(bounds.len() * 2),
+            indices: (0..bounds.len() as u32).collect(),
+        };
+
+        if !bounds.is_empty() {
+            let centroids = bounds.iter().map(Aabb::centroid).collect::<Vec<_>>();
+            tree.build_node(bounds, &centroids, 0, bounds.len(), 0);
+        }
+
+        tree
+    }
+
+    fn build_node(
+        &mut self,
+        bounds: &[Aabb],
+        centroids: &[Vec3],
+        start: usize,
+        end: usize,
+        depth: usize,
+    ) -> usize {
+        let index = self.nodes.len();
+        let indices = &mut self.indices[start..end];
+
+        let node_bounds = indices
+            .iter()
+            .fold(Aabb::EMPTY, |b, i| b.union(&bounds[*i as usize]));
+        let centroid_bounds = Aabb::from_points(indices.iter().map(|i| centroids[*i as usize]));
+
+        self.nodes.push(Node {
+            bounds: node_bounds,
+            offset: start as u32,
+            count: indices.len() as u32,
+            axis: 0,
+        });
+
+        let count = indices.len();
+        if count <= 2 {
+            return index;
+        }
+
+        let axis = centroid_bounds.longest_axis();
+        let axis_min = centroid_bounds.min[axis];
+        let axis_extent = centroid_bounds.extent()[axis];
+
+        let mid = if axis_extent <= 0. {
+            // Every centroid is in the same spot, there is nothing to gain from splitting
+            if count <= MAX_LEAF_SIZE {
+                return index;
+            }
+            count / 2
+        } else if depth >= MAX_SAH_DEPTH {
+            indices.select_nth_unstable_by(count / 2, |a, b| {
+                centroids[*a as usize][axis].total_cmp(&centroids[*b as usize][axis])
+            });
+            count / 2
+        } else {
+            let bin_of = |i: u32| {
+                let relative = (centroids[i as usize][axis] - axis_min) / axis_extent;
+                ((relative * BINS as f32) as usize).min(BINS - 1)
+            };
+
+            let mut bin_bounds = [Aabb::EMPTY; BINS];
+            let mut bin_counts = [0usize; BINS];
+            for i in indices.iter() {
+                let bin = bin_of(*i);
+                bin_bounds[bin] = bin_bounds[bin].union(&bounds[*i as usize]);
+                bin_counts[bin] += 1;
+            }
+
+            // Cost of every split plane, sweeping from the right first
+            let mut right_costs = [0.; BINS];
+            let mut right = Aabb::EMPTY;
+            let mut right_count = 0;
+            for split in (1..BINS).rev() {
+                right = right.union(&bin_bounds[split]);
+                right_count += bin_counts[split];
+                right_costs[split] = right.surface_area() * right_count as f32;
+            }
+
+            let mut best = (f32::INFINITY, 0);
+            let mut left = Aabb::EMPTY;
+            let mut left_count = 0;
+            for split in 1..BINS {
+                left = left.union(&bin_bounds[split - 1]);
+                left_count += bin_counts[split - 1];
+                let cost = left.surface_area() * left_count as f32 + right_costs[split];
+                if cost < best.0 {
+                    best = (cost, split);
+                }
+            }
+
+            let split_cost =
+                TRAVERSAL_COST + best.0 / node_bounds.surface_area().max(f32::MIN_POSITIVE);
+            if split_cost >= count as f32 && count <= MAX_LEAF_SIZE {
+                return index;
+            }
+
+            let mut mid = 0;
+            for i in 0..count {
+                if bin_of(indices[i]) < best.1 {
+                    indices.swap(i, mid);
+                    mid += 1;
+                }
+            }
+
+            if mid == 0 || mid == count {
+                count / 2
+            } else {
+                mid
+            }
+        };
+
+        self.build_node(bounds, centroids, start, start + mid, depth + 1);
+        let right = self.build_node(bounds, centroids, start + mid, end, depth + 1);
+
+        let node = &mut self.nodes[index];
+        node.offset = right as u32;
+        node.count = 0;
+        node.axis = axis as u8;
+
+        index
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.nodes.first().map(|n| n.bounds).unwrap_or(Aabb::EMPTY)
+    }
+
+    /// Finds the closest intersection along the ray.
+    ///
+    /// `intersect` is called with the index of a primitive whose bounds are hit
+    /// and the current closest distance, and returns the distance of its own hit.
+    pub fn traverse<R>(
+        &self,
+        ray: &Ray,
+        ray_t_min: f32,
+        ray_t_max: f32,
+        mut intersect: impl FnMut(usize, f32) -> Option<(f32, R)>,
+    ) -> Option<R> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let inverse_direction = ray.direction.recip();
+        let negative = inverse_direction.cmplt(Vec3::ZERO);
+
+        let mut closest = ray_t_max;
+        let mut result = None;
+
+        let mut stack = [0u32; STACK_SIZE];
+        let mut stack_len = 1;
+
+        while stack_len > 0 {
+            stack_len -= 1;
+            let node_index = stack[stack_len];
+            let node = &self.nodes[node_index as usize];
+
+            if !node
+                .bounds
+                .hit(ray.origin, inverse_direction, ray_t_min, closest)
+            {
+                continue;
+            }
+
+            if node.count > 0 {
+                let start = node.offset as usize;
+                for primitive in &self.indices[start..start + node.count as usize] {
+                    if let Some((distance, hit)) = intersect(*primitive as usize, closest) {
+                        closest = distance;
+                        result = Some(hit);
+                    }
+                }
+                continue;
+            }
+
+            let left = node_index + 1;
+            let right = node.offset;
+
+            // Visit the closest child first, so the farther one can be culled
+            let (near, far) = if negative.test(node.axis as usize) {
+                (right, left)
+            } else {
+                (left, right)
+            };
+
+            stack[stack_len] = far;
+            stack[stack_len + 1] = near;
+            stack_len += 2;
+        }
+
+        result
+    }
+}
+
+/// Acceleration structure over a list of meshes, itself a [`Mesh`]
+pub struct Bvh {
+    tree: BvhTree,
+    /// Meshes along with their index in the list the BVH was built from
+    meshes: Vec<(usize, Arc<dyn Mesh>)>,
+    /// Meshes without finite bounds, always tested
+    unbounded: Vec<(usize, Arc<dyn Mesh>)>,
+}
+
+impl Bvh {
+    pub fn new(meshes: Vec<Arc<dyn Mesh>>) -> Bvh {
+        let (meshes, unbounded): (Vec<_>, Vec<_>) = meshes
+            .into_iter()
+            .enumerate()
+            .partition(|(_, m)| m.bounding_box().is_finite());
+
+        let bounds = meshes
+            .iter()
+            .map(|(_, m)| m.bounding_box())
+            .collect::<Vec<_>>();
+
+        Bvh {
+            tree: BvhTree::build(&bounds),
+            meshes,
+            unbounded,
+        }
+    }
+
+    /// Closest hit, along with the index of the mesh hit in the list given to [`Bvh::new`]
+    pub fn hit_index(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<(usize, Hit)> {
+        let mut closest = self
+            .tree
+            .traverse(ray, ray_t_min, ray_t_max, |i, distance| {
+                let (index, mesh) = &self.meshes[i];
+                mesh.hit(ray, ray_t_min, distance)
+                    .map(|hit| (hit.distance, (*index, hit)))
+            });
+
+        for (index, mesh) in &self.unbounded {
+            let distance = closest
+                .as_ref()
+                .map(|(_, h)| h.distance)
+                .unwrap_or(ray_t_max);
+            if let Some(hit) = mesh.hit(ray, ray_t_min, distance) {
+                closest = Some((*index, hit));
+            }
+        }
+
+        closest
+    }
+}
+
+impl Mesh for Bvh {
+    fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit> {
+        self.hit_index(ray, ray_t_min, ray_t_max)
+            .map(|(_, hit)| hit)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        if self.unbounded.is_empty() {
+            self.tree.bounds()
+        } else {
+            Aabb::INFINITE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate test;
+
+    use std::sync::Arc;
+
+    use glam::Vec3;
+    use test::Bencher;
+
+    use super::Bvh;
+    use crate::utils::{
+        colors::BLACK,
+        materials::metal::Metal,
+        meshes::{sphere::Sphere, Mesh},
+        ray::Ray,
+    };
+
+    /// Deterministic pseudo random numbers, so failures can be reproduced
+    fn lcg(state: &mut u32) -> f32 {
+        *state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+        (*state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn spheres(count: usize) -> Vec<Arc<dyn Mesh>> {
+        let mut state = 42;
+        (0..count)
+            .map(|_| {
+                let center = Vec3::new(lcg(&mut state), lcg(&mut state), lcg(&mut state)) * 20.
+                    - Vec3::splat(10.);
+                Arc::new(Sphere::new(
+                    center,
+                    lcg(&mut state) * 0.5 + 0.05,
+                    Metal::new(BLACK),
+                )) as Arc<dyn Mesh>
+            })
+            .collect()
+    }
+
+    fn rays(count: usize) -> Vec<Ray> {
+        let mut state = 7;
+        (0..count)
+            .map(|_| {
+                let direction =
+                    Vec3::new(lcg(&mut state), lcg(&mut state), lcg(&mut state)) * 2. - Vec3::ONE;
+                Ray::new(Vec3::new(0., 0., 15.), direction)
+            })
+            .collect()
+    }
+
+    fn linear(meshes: &[Arc<dyn Mesh>], ray: &Ray) -> Option<f32> {
+        meshes
+            .iter()
+            .filter_map(|m| m.hit(ray, 0.001, f32::INFINITY))
+            .map(|h| h.distance)
+            .min_by(f32::total_cmp)
+    }
+
+    #[test]
+    fn same_hits_as_linear_search() {
+        let meshes = spheres(500);
+        let bvh = Bvh::new(meshes.clone());
+
+        for ray in rays(2000) {
+            assert_eq!(
+                bvh.hit(&ray, 0.001, f32::INFINITY).map(|h| h.distance),
+                linear(&meshes, &ray)
+            );
+        }
+    }
+
+    #[test]
+    fn bounds_contain_every_mesh() {
+        let meshes = spheres(100);
+        let bvh = Bvh::new(meshes.clone());
+
+        for mesh in meshes {
+            assert_eq!(
+                bvh.bounding_box().union(&mesh.bounding_box()),
+                bvh.bounding_box()
+            );
+        }
+    }
+
+    #[test]
+    fn empty_and_single() {
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(0., 0., -1.));
+        assert!(Bvh::new(vec![]).hit(&ray, 0.001, f32::INFINITY).is_none());
+
+        let single = Bvh::new(vec![Arc::new(Sphere::new(
+            Vec3::new(0., 0., -2.),
+            0.5,
+            Metal::new(BLACK),
+        ))]);
+        assert_eq!(
+            single.hit(&ray, 0.001, f32::INFINITY).map(|h| h.distance),
+            Some(1.5)
+        );
+    }
+
+    #[bench]
+    fn hit_bvh(b: &mut Bencher) {
+        let bvh = Bvh::new(spheres(1000));
+        let rays = rays(100);
+        b.iter(|| {
+            rays.iter()
+                .map(|r| bvh.hit(r, 0.001, f32::INFINITY).map(|h| h.distance))
+                .collect::<Vec<_>>()
+        })
+    }
+
+    #[bench]
+    fn hit_linear(b: &mut Bencher) {
+        let meshes = spheres(1000);
+        let rays = rays(100);
+        b.iter(|| rays.iter().map(|r| linear(&meshes, r)).collect::<Vec<_>>())
+    }
+}
@@ -0,0 +1,121 @@
+use std::{f32::consts::TAU, sync::Arc};
+
+use glam::Vec3;
+
+use crate::utils::{aabb::Aabb, materials::Material, ray::Ray, roots};
+
+use super::{disk::intersect_cap, frame::Frame, Hit, Mesh};
+
+/// Cone closed by a disk of `radius` at `base`, narrowing down to `apex`.
+///
+/// On the side `u` goes around the axis and `v` from the base to the apex,
+/// the base is mapped like a disk.
+#[derive(Debug, Clone)]
+pub struct Cone {
+    frame: Frame,
+    height: f32,
+    radius: f32,
+    material: Arc<dyn Material>,
+}
+
+impl Cone {
+    pub fn new(base: Vec3, apex: Vec3, radius: f32, material: Arc<dyn Material>) -> Cone {
+        Cone {
+            frame: Frame::new(base, apex - base),
+            height: (apex - base).length(),
+            radius,
+            material,
+        }
+    }
+}
+
+impl Mesh for Cone {
+    fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit> {
+        let local = self.frame.to_local(ray);
+        let (o, d) = (local.origin, local.direction);
+
+        // Radius over height, the side is x² + z² = (k (h - y))²
+        let k = self.radius / self.height;
+        let k2 = k * k;
+        let h = self.height - o.y;
+
+        let mut closest: Option<(f32, Vec3, (f32, f32))> = None;
+
+        if let Some((t0, t1)) = roots::quadratic(
+            d.x * d.x + d.z * d.z - k2 * d.y * d.y,
+            2. * (o.x * d.x + o.z * d.z + k2 * h * d.y),
+            o.x * o.x + o.z * o.z - k2 * h * h,
+        ) {
+            // The equation also describes the mirrored cone above the apex
+            closest = [t0, t1].into_iter().find_map(|t| {
+                let p = local.at(t);
+                (ray_t_min < t && t < ray_t_max && (0. ..=self.height).contains(&p.y)).then(|| {
+                    let angle = p.z.atan2(p.x).rem_euclid(TAU);
+                    let normal =
+                        Vec3::new(p.x, k2 * (self.height - p.y), p.z).normalize_or(Vec3::Y);
+                    (t, normal, (angle / TAU, p.y / self.height))
+                })
+            });
+        }
+
+        if let Some((t, uv)) = intersect_cap(&local, 0., self.radius, ray_t_min, ray_t_max) {
+            if closest.is_none_or(|(closest, _, _)| t < closest) {
+                closest = Some((t, Vec3::NEG_Y, uv));
+            }
+        }
+
+        let (distance, normal, uv) = closest?;
+
+        Some(Hit::new(
+            ray,
+            distance,
+            self.frame.vector_to_world(normal),
+            uv,
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = self.radius;
+        self.frame.bounding_box(Aabb::new(
+            Vec3::new(-r, 0., -r),
+            Vec3::new(r, self.height, r),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::Cone;
+    use crate::utils::{colors::BLACK, materials::metal::Metal, meshes::Mesh, ray::Ray};
+
+    #[test]
+    fn side_and_base() {
+        // 45 degrees, 1 wide and 1 high
+        let cone = Cone::new(
+            Vec3::new(0., 0., -3.),
+            Vec3::new(0., 1., -3.),
+            1.,
+            Metal::new(BLACK),
+        );
+
+        let ray = Ray::new(Vec3::new(0., 0.5, 0.), Vec3::NEG_Z);
+        let hit = cone.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 2.5).abs() < 1e-5);
+        assert!(hit
+            .normal
+            .abs_diff_eq(Vec3::new(0., 1., 1.).normalize(), 1e-5));
+        assert!((hit.v - 0.5).abs() < 1e-5);
+
+        let ray = Ray::new(Vec3::new(0.5, -2., -3.), Vec3::Y);
+        let hit = cone.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 2.).abs() < 1e-5);
+        assert!(hit.normal.abs_diff_eq(Vec3::NEG_Y, 1e-5));
+
+        // Misses the mirrored cone above the apex
+        let ray = Ray::new(Vec3::new(0., 1.5, 0.), Vec3::NEG_Z);
+        assert!(cone.hit(&ray, 0.001, f32::INFINITY).is_none());
+    }
+}
@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use glam::Vec3;
+
+use crate::utils::{aabb::Aabb, materials::Material, ray::Ray};
+
+use super::{Hit, Mesh};
+
+/// Volume of fog or smoke filling a closed `boundary`, its own material is ignored.
+///
+/// Rays travel a random distance inside before scattering, following the
+/// exponential falloff of a uniform density, and go through when the distance
+/// is longer than the way out. The `phase` material decides where they scatter to,
+/// usually [`Isotropic`](crate::utils::materials::isotropic::Isotropic).
+#[derive(Clone)]
+pub struct ConstantMedium {
+    boundary: Arc<dyn Mesh>,
+    density: f32,
+    phase: Arc<dyn Material>,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Arc<dyn Mesh>, density: f32, phase: Arc<dyn Material>) -> ConstantMedium {
+        ConstantMedium {
+            boundary,
+            density,
+            phase,
+        }
+    }
+}
+
+impl std::fmt::Debug for ConstantMedium {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConstantMedium")
+            .field("density", &self.density)
+            .field("phase", &self.phase)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Mesh for ConstantMedium {
+    fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit> {
+        // Where the ray enters and leaves the boundary, even when it starts inside
+        let enter = self.boundary.hit(ray, f32::NEG_INFINITY, f32::INFINITY)?;
+        let leave = self
+            .boundary
+            .hit(ray, enter.distance + 1e-4, f32::INFINITY)?;
+
+        let enter = enter.distance.max(ray_t_min).max(0.);
+        let leave = leave.distance.min(ray_t_max);
+        if enter >= leave {
+            return None;
+        }
+
+        let length = ray.direction.length();
+        let inside = (leave - enter) * length;
+        let travelled = -rand::random::<f32>().ln() / self.density;
+        if travelled > inside {
+            return None;
+        }
+
+        let distance = enter + travelled / length;
+
+        Some(Hit {
+            distance,
+            point: ray.at(distance),
+            // Isotropic scattering does not look at the normal
+            normal: Vec3::X,
+            front_face: true,
+            u: 0.,
+            v: 0.,
+            material: self.phase.clone(),
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use glam::Vec3;
+
+    use super::ConstantMedium;
+    use crate::utils::{
+        colors::{BLACK, WHITE},
+        materials::{isotropic::Isotropic, metal::Metal},
+        meshes::{cuboid::Cuboid, Mesh},
+        ray::Ray,
+    };
+
+    fn fog(density: f32) -> ConstantMedium {
+        let boundary = Arc::new(Cuboid::new(
+            Vec3::new(-1., -1., -3.),
+            Vec3::new(1., 1., -1.),
+            Metal::new(BLACK),
+        ));
+        ConstantMedium::new(boundary, density, Isotropic::new(WHITE))
+    }
+
+    #[test]
+    fn hits_stay_inside_the_boundary() {
+        let fog = fog(100.);
+
+        // Starting outside and starting inside
+        for origin in [Vec3::ZERO, Vec3::new(0., 0., -2.)] {
+            let ray = Ray::new(origin, Vec3::new(0., 0., -2.));
+            let hit = fog.hit(&ray, 0.001, f32::INFINITY).unwrap();
+            assert!((-3. ..=-1.).contains(&hit.point.z), "{}", hit.point);
+            // Dense fog is hit right after entering it
+            let entry = origin.z.min(-1.);
+            assert!(hit.point.z > entry - 0.1, "{}", hit.point);
+        }
+
+        let ray = Ray::new(Vec3::new(2., 0., 0.), Vec3::NEG_Z);
+        assert!(fog.hit(&ray, 0.001, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn transmittance_follows_the_density() {
+        let fog = fog(0.5);
+        let ray = Ray::new(Vec3::ZERO, Vec3::NEG_Z);
+
+        let count = 20_000;
+        let through = (0..count)
+            .filter(|_| fog.hit(&ray, 0.001, f32::INFINITY).is_none())
+            .count();
+
+        // 2 units of fog let e^(-0.5 * 2) of the rays through
+        let expected = (-1_f32).exp();
+        assert!((through as f32 / count as f32 - expected).abs() < 0.02);
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use glam::Vec3;
+
+use crate::utils::{aabb::Aabb, materials::Material, ray::Ray};
+
+use super::{quad::Quad, Hit, Mesh};
+
+/// Axis aligned box between two opposite corners, made of six quads facing outwards
+#[derive(Debug, Clone)]
+pub struct Cuboid {
+    aabb: Aabb,
+    faces: [Quad; 6],
+}
+
+impl Cuboid {
+    pub fn new(a: Vec3, b: Vec3, material: Arc<dyn Material>) -> Cuboid {
+        let (min, max) = (a.min(b), a.max(b));
+        let size = max - min;
+        let (dx, dy, dz) = (
+            Vec3::new(size.x, 0., 0.),
+            Vec3::new(0., size.y, 0.),
+            Vec3::new(0., 0., size.z),
+        );
+
+        let face = |corner: Vec3, u: Vec3, v: Vec3| Quad::new(corner, u, v, material.clone());
+
+        Cuboid {
+            aabb: Aabb::new(min, max),
+            faces: [
+                // Front, right, back, left, top, bottom
+                face(Vec3::new(min.x, min.y, max.z), dx, dy),
+                face(Vec3::new(max.x, min.y, max.z), -dz, dy),
+                face(Vec3::new(max.x, min.y, min.z), -dx, dy),
+                face(min, dz, dy),
+                face(Vec3::new(min.x, max.y, max.z), dx, -dz),
+                face(min, dx, dz),
+            ],
+        }
+    }
+}
+
+impl Mesh for Cuboid {
+    fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit> {
+        self.faces.iter().fold(None, |closest, face| {
+            let t_max = closest.as_ref().map_or(ray_t_max, |hit: &Hit| hit.distance);
+            face.hit(ray, ray_t_min, t_max).or(closest)
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.aabb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::Cuboid;
+    use crate::utils::{colors::BLACK, materials::metal::Metal, meshes::Mesh, ray::Ray};
+
+    #[test]
+    fn faces_point_outwards() {
+        let cuboid = Cuboid::new(Vec3::splat(1.), Vec3::splat(-1.), Metal::new(BLACK));
+
+        for axis in [
+            Vec3::X,
+            Vec3::Y,
+            Vec3::Z,
+            Vec3::NEG_X,
+            Vec3::NEG_Y,
+            Vec3::NEG_Z,
+        ] {
+            let ray = Ray::new(axis * 3. + 0.1, -axis);
+            let hit = cuboid.hit(&ray, 0.001, f32::INFINITY).unwrap();
+            assert!((hit.distance - 2.).abs() < 0.2);
+            assert!(hit.front_face, "{axis}");
+            assert_eq!(hit.normal, axis);
+
+            // From the inside the far face is hit from its back
+            let ray = Ray::new(Vec3::ZERO, axis);
+            let hit = cuboid.hit(&ray, 0.001, f32::INFINITY).unwrap();
+            assert!((hit.distance - 1.).abs() < 1e-6);
+            assert!(!hit.front_face, "{axis}");
+            assert_eq!(hit.normal, -axis);
+        }
+    }
+}
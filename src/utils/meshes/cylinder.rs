@@ -0,0 +1,131 @@
+use std::{f32::consts::TAU, sync::Arc};
+
+use glam::Vec3;
+
+use crate::utils::{aabb::Aabb, materials::Material, ray::Ray, roots};
+
+use super::{disk::intersect_cap, frame::Frame, Hit, Mesh};
+
+/// Cylinder closed by two disks, going from the center of `base` to the center of `top`.
+///
+/// On the side `u` goes around the axis and `v` from the base to the top,
+/// the caps are mapped like disks.
+#[derive(Debug, Clone)]
+pub struct Cylinder {
+    frame: Frame,
+    height: f32,
+    radius: f32,
+    material: Arc<dyn Material>,
+}
+
+impl Cylinder {
+    pub fn new(base: Vec3, top: Vec3, radius: f32, material: Arc<dyn Material>) -> Cylinder {
+        Cylinder {
+            frame: Frame::new(base, top - base),
+            height: (top - base).length(),
+            radius,
+            material,
+        }
+    }
+}
+
+impl Mesh for Cylinder {
+    fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit> {
+        let local = self.frame.to_local(ray);
+        let (o, d) = (local.origin, local.direction);
+
+        let mut closest: Option<(f32, Vec3, (f32, f32))> = None;
+        let mut consider = |distance: f32, normal: Vec3, uv: (f32, f32)| {
+            if closest.is_none_or(|(closest, _, _)| distance < closest) {
+                closest = Some((distance, normal, uv));
+            }
+        };
+
+        // x² + z² = r²
+        if let Some((t0, t1)) = roots::quadratic(
+            d.x * d.x + d.z * d.z,
+            2. * (o.x * d.x + o.z * d.z),
+            o.x * o.x + o.z * o.z - self.radius * self.radius,
+        ) {
+            for t in [t0, t1] {
+                let p = local.at(t);
+                if ray_t_min < t && t < ray_t_max && (0. ..=self.height).contains(&p.y) {
+                    let angle = p.z.atan2(p.x).rem_euclid(TAU);
+                    consider(
+                        t,
+                        Vec3::new(p.x, 0., p.z) / self.radius,
+                        (angle / TAU, p.y / self.height),
+                    );
+                }
+            }
+        }
+
+        for (y, normal) in [(0., Vec3::NEG_Y), (self.height, Vec3::Y)] {
+            if let Some((t, uv)) = intersect_cap(&local, y, self.radius, ray_t_min, ray_t_max) {
+                consider(t, normal, uv);
+            }
+        }
+
+        let (distance, normal, uv) = closest?;
+
+        Some(Hit::new(
+            ray,
+            distance,
+            self.frame.vector_to_world(normal),
+            uv,
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = self.radius;
+        self.frame.bounding_box(Aabb::new(
+            Vec3::new(-r, 0., -r),
+            Vec3::new(r, self.height, r),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::Cylinder;
+    use crate::utils::{colors::BLACK, materials::metal::Metal, meshes::Mesh, ray::Ray};
+
+    fn cylinder() -> Cylinder {
+        Cylinder::new(
+            Vec3::new(0., -1., -3.),
+            Vec3::new(0., 1., -3.),
+            1.,
+            Metal::new(BLACK),
+        )
+    }
+
+    #[test]
+    fn side_and_caps() {
+        let c = cylinder();
+
+        let ray = Ray::new(Vec3::ZERO, Vec3::NEG_Z);
+        let hit = c.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 2.).abs() < 1e-5);
+        assert!(hit.normal.abs_diff_eq(Vec3::Z, 1e-5));
+        assert!(hit.front_face);
+        assert!((hit.v - 0.5).abs() < 1e-5);
+
+        let ray = Ray::new(Vec3::new(0.5, 5., -3.), Vec3::NEG_Y);
+        let hit = c.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 4.).abs() < 1e-5);
+        assert!(hit.normal.abs_diff_eq(Vec3::Y, 1e-5));
+
+        // From the inside the normal points inwards
+        let ray = Ray::new(Vec3::new(0., 0., -3.), Vec3::X);
+        let hit = c.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!(!hit.front_face);
+        assert!(hit.normal.abs_diff_eq(Vec3::NEG_X, 1e-5));
+
+        // Above the top cap
+        let ray = Ray::new(Vec3::new(0., 1.5, 0.), Vec3::NEG_Z);
+        assert!(c.hit(&ray, 0.001, f32::INFINITY).is_none());
+    }
+}
@@ -0,0 +1,104 @@
+use std::{f32::consts::TAU, sync::Arc};
+
+use glam::Vec3;
+
+use crate::utils::{aabb::Aabb, materials::Material, ray::Ray};
+
+use super::{frame::Frame, Hit, Mesh};
+
+/// Flat circle, the front face is the side `normal` points to.
+///
+/// `u` goes around the center and `v` from the center to the edge.
+#[derive(Debug, Clone)]
+pub struct Disk {
+    frame: Frame,
+    radius: f32,
+    material: Arc<dyn Material>,
+}
+
+impl Disk {
+    pub fn new(center: Vec3, normal: Vec3, radius: f32, material: Arc<dyn Material>) -> Disk {
+        Disk {
+            frame: Frame::new(center, normal),
+            radius,
+            material,
+        }
+    }
+}
+
+/// Intersection with the disk of `radius` at height `y` of the local space,
+/// returns the distance and the polar texture coordinates
+pub fn intersect_cap(
+    local: &Ray,
+    y: f32,
+    radius: f32,
+    ray_t_min: f32,
+    ray_t_max: f32,
+) -> Option<(f32, (f32, f32))> {
+    if local.direction.y.abs() < 1e-8 {
+        return None;
+    }
+
+    let distance = (y - local.origin.y) / local.direction.y;
+    if distance <= ray_t_min || ray_t_max <= distance {
+        return None;
+    }
+
+    let point = local.at(distance);
+    let r = (point.x * point.x + point.z * point.z).sqrt();
+    if r > radius {
+        return None;
+    }
+
+    let angle = point.z.atan2(point.x).rem_euclid(TAU);
+    Some((distance, (angle / TAU, r / radius)))
+}
+
+impl Mesh for Disk {
+    fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit> {
+        let local = self.frame.to_local(ray);
+        let (distance, uv) = intersect_cap(&local, 0., self.radius, ray_t_min, ray_t_max)?;
+
+        Some(Hit::new(
+            ray,
+            distance,
+            self.frame.axis(),
+            uv,
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = self.radius;
+        // Flat disks are padded like quads
+        self.frame
+            .bounding_box(Aabb::new(Vec3::new(-r, -1e-4, -r), Vec3::new(r, 1e-4, r)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::Disk;
+    use crate::utils::{colors::BLACK, materials::metal::Metal, meshes::Mesh, ray::Ray};
+
+    #[test]
+    fn hit_inside_the_radius() {
+        let disk = Disk::new(Vec3::new(0., 0., -2.), Vec3::Z, 1., Metal::new(BLACK));
+
+        let ray = Ray::new(Vec3::new(0.5, 0., 0.), Vec3::NEG_Z);
+        let hit = disk.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 2.).abs() < 1e-6);
+        assert!(hit.front_face);
+        assert!(hit.normal.abs_diff_eq(Vec3::Z, 1e-6));
+        assert!((hit.v - 0.5).abs() < 1e-6);
+
+        let ray = Ray::new(Vec3::new(0.8, 0.8, 0.), Vec3::NEG_Z);
+        assert!(disk.hit(&ray, 0.001, f32::INFINITY).is_none());
+
+        let aabb = disk.bounding_box();
+        assert!(aabb.min.abs_diff_eq(Vec3::new(-1., -1., -2.), 1e-3));
+        assert!(aabb.max.abs_diff_eq(Vec3::new(1., 1., -2.), 1e-3));
+    }
+}
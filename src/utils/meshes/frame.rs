@@ -0,0 +1,77 @@
+use glam::{Mat3, Vec3};
+
+use crate::utils::{aabb::Aabb, ray::Ray};
+
+/// Position and orientation of a primitive, which is intersected in its own space
+/// where it sits at the origin around the Y axis
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    origin: Vec3,
+    /// Columns are the local X, Y and Z axes, orthonormal so the inverse is the transpose
+    rotation: Mat3,
+}
+
+impl Frame {
+    pub fn new(origin: Vec3, axis: Vec3) -> Frame {
+        let y = axis.normalize();
+        let (z, x) = y.any_orthonormal_pair();
+
+        Frame {
+            origin,
+            rotation: Mat3::from_cols(x, y, z),
+        }
+    }
+
+    pub fn axis(&self) -> Vec3 {
+        self.rotation.y_axis
+    }
+
+    /// The distances along the local ray are the same as along `ray`
+    pub fn to_local(&self, ray: &Ray) -> Ray {
+        let inverse = self.rotation.transpose();
+        Ray::new(
+            inverse * (ray.origin - self.origin),
+            inverse * ray.direction,
+        )
+        .with_time(ray.time)
+    }
+
+    pub fn point_to_world(&self, point: Vec3) -> Vec3 {
+        self.origin + self.rotation * point
+    }
+
+    pub fn vector_to_world(&self, vector: Vec3) -> Vec3 {
+        self.rotation * vector
+    }
+
+    /// World box around a box of the local space
+    pub fn bounding_box(&self, local: Aabb) -> Aabb {
+        Aabb::from_points(local.corners().map(|corner| self.point_to_world(corner)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::Frame;
+    use crate::utils::{aabb::Aabb, ray::Ray};
+
+    #[test]
+    fn round_trip() {
+        let frame = Frame::new(Vec3::new(1., 2., 3.), Vec3::new(1., 1., 0.));
+        assert!(frame
+            .vector_to_world(Vec3::Y)
+            .abs_diff_eq(Vec3::new(1., 1., 0.).normalize(), 1e-6));
+
+        let ray = Ray::new(Vec3::new(4., 5., 6.), Vec3::new(0., 0., -2.));
+        let local = frame.to_local(&ray);
+        assert!(frame
+            .point_to_world(local.at(1.5))
+            .abs_diff_eq(ray.at(1.5), 1e-5));
+
+        let aabb = frame.bounding_box(Aabb::new(Vec3::splat(-1.), Vec3::splat(1.)));
+        assert!(aabb.min.cmple(Vec3::new(0., 1., 2.)).all());
+        assert!(aabb.max.cmpge(Vec3::new(2., 3., 4.)).all());
+    }
+}
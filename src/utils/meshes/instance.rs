@@ -0,0 +1,327 @@
+use std::sync::Arc;
+
+use glam::{Affine3A, Mat3A, Quat, Vec3};
+
+use crate::utils::{aabb::Aabb, ray::Ray};
+
+use super::{Hit, Mesh};
+
+/// A transform with what is needed to move rays and normals back and forth
+#[derive(Debug, Clone, Copy)]
+struct Transform {
+    forward: Affine3A,
+    inverse: Affine3A,
+    /// Inverse transpose of the linear part, keeps normals perpendicular to the surface
+    normal_matrix: Mat3A,
+}
+
+impl Transform {
+    fn new(forward: Affine3A) -> Transform {
+        let inverse = forward.inverse();
+
+        Transform {
+            forward,
+            inverse,
+            normal_matrix: inverse.matrix3.transpose(),
+        }
+    }
+
+    /// The direction is not normalized, so distances along both rays are the same
+    fn local_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            self.inverse.transform_point3(ray.origin),
+            self.inverse.transform_vector3(ray.direction),
+        )
+        .with_time(ray.time)
+    }
+}
+
+/// Scale, rotation and translation of both ends of a motion
+#[derive(Debug, Clone, Copy)]
+struct Motion {
+    start: (Vec3, Quat, Vec3),
+    end: (Vec3, Quat, Vec3),
+}
+
+impl Motion {
+    /// Steps used to bound the swept volume of a rotation
+    const STEPS: usize = 32;
+
+    fn at(&self, time: f32) -> Affine3A {
+        let t = time.clamp(0., 1.);
+        let (scale, rotation, translation) = self.start;
+        let (end_scale, end_rotation, end_translation) = self.end;
+
+        Affine3A::from_scale_rotation_translation(
+            scale.lerp(end_scale, t),
+            rotation.slerp(end_rotation, t),
+            translation.lerp(end_translation, t),
+        )
+    }
+}
+
+/// Places a mesh in the scene through an affine transform, so the same geometry
+/// can be shared by many instances.
+///
+/// Rays are moved to the space of the mesh instead of moving the mesh.
+#[derive(Clone)]
+pub struct Instance {
+    mesh: Arc<dyn Mesh>,
+    transform: Transform,
+    motion: Option<Motion>,
+    aabb: Aabb,
+}
+
+impl Instance {
+    pub fn new(mesh: Arc<dyn Mesh>, transform: Affine3A) -> Instance {
+        let aabb = Self::transformed_box(mesh.bounding_box(), &transform);
+
+        Instance {
+            mesh,
+            transform: Transform::new(transform),
+            motion: None,
+            aabb,
+        }
+    }
+
+    /// Instance going from `start` at time 0 to `end` at time 1, still before and after.
+    /// Scales, rotations and translations are interpolated separately, so shears are lost
+    pub fn moving(mesh: Arc<dyn Mesh>, start: Affine3A, end: Affine3A) -> Instance {
+        let motion = Motion {
+            start: start.to_scale_rotation_translation(),
+            end: end.to_scale_rotation_translation(),
+        };
+
+        let inner = mesh.bounding_box();
+        let mut aabb = (0..=Motion::STEPS).fold(Aabb::EMPTY, |aabb, step| {
+            let transform = motion.at(step as f32 / Motion::STEPS as f32);
+            aabb.union(&Self::transformed_box(inner, &transform))
+        });
+
+        // Between two steps the rotating corners bulge out of the boxes at most by this
+        if aabb.is_finite() {
+            let angle = motion.start.1.angle_between(motion.end.1) / Motion::STEPS as f32;
+            let radius = inner
+                .corners()
+                .iter()
+                .map(|c| c.length())
+                .fold(0., f32::max)
+                * motion.start.0.max(motion.end.0).max_element();
+            let bulge = radius * (1. - (angle / 2.).cos()) + 1e-4;
+            aabb = Aabb::new(aabb.min - bulge, aabb.max + bulge);
+        }
+
+        Instance {
+            mesh,
+            transform: Transform::new(motion.at(0.)),
+            motion: Some(motion),
+            aabb,
+        }
+    }
+
+    fn transformed_box(inner: Aabb, transform: &Affine3A) -> Aabb {
+        if inner.is_finite() {
+            Aabb::from_points(inner.corners().map(|c| transform.transform_point3(c)))
+        } else {
+            Aabb::INFINITE
+        }
+    }
+
+    fn transform_at(&self, time: f32) -> Transform {
+        match self.motion {
+            Some(motion) => Transform::new(motion.at(time)),
+            None => self.transform,
+        }
+    }
+
+    /// Rotations, translations and uniform scales keep solid angles, so the inner
+    /// mesh can be sampled as a light
+    fn keeps_angles(&self) -> bool {
+        let m = self.transform.forward.matrix3;
+        let scale = m.x_axis.length_squared();
+
+        [m.y_axis.length_squared(), m.z_axis.length_squared()]
+            .iter()
+            .all(|s| (s - scale).abs() <= 1e-4 * scale)
+            && [
+                m.x_axis.dot(m.y_axis),
+                m.y_axis.dot(m.z_axis),
+                m.z_axis.dot(m.x_axis),
+            ]
+            .iter()
+            .all(|d| d.abs() <= 1e-4 * scale)
+    }
+}
+
+impl std::fmt::Debug for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Instance")
+            .field("transform", &self.transform.forward)
+            .field("motion", &self.motion)
+            .field("aabb", &self.aabb)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Mesh for Instance {
+    fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit> {
+        let transform = self.transform_at(ray.time);
+        let mut hit = self
+            .mesh
+            .hit(&transform.local_ray(ray), ray_t_min, ray_t_max)?;
+
+        hit.point = ray.at(hit.distance);
+        // Facing the local ray is the same as facing the world ray with this normal
+        hit.normal = (transform.normal_matrix * hit.normal).normalize();
+
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.aabb
+    }
+
+    /// Lights are sampled without knowing the time, so moving ones are only found by chance
+    fn is_light(&self) -> bool {
+        self.motion.is_none() && self.mesh.is_light() && self.keeps_angles()
+    }
+
+    fn sample(&self, origin: Vec3) -> Option<Vec3> {
+        let transform = &self.transform;
+        let direction = self
+            .mesh
+            .sample(transform.inverse.transform_point3(origin))?;
+        Some(transform.forward.transform_vector3(direction))
+    }
+
+    fn pdf(&self, ray: &Ray) -> f32 {
+        self.mesh.pdf(&self.transform.local_ray(ray))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use glam::{Affine3A, Quat, Vec3};
+
+    use super::Instance;
+    use crate::utils::{
+        colors::BLACK,
+        materials::{diffuse_light::DiffuseLight, metal::Metal},
+        meshes::{cuboid::Cuboid, sphere::Sphere, Mesh},
+        ray::Ray,
+    };
+
+    #[test]
+    fn moved_rotated_and_scaled() {
+        let cube = Arc::new(Cuboid::new(Vec3::ZERO, Vec3::ONE, Metal::new(BLACK)));
+        let instance = Instance::new(
+            cube,
+            Affine3A::from_scale_rotation_translation(
+                Vec3::new(2., 1., 1.),
+                Quat::from_rotation_y(90_f32.to_radians()),
+                Vec3::new(0., 0., -5.),
+            ),
+        );
+
+        // The unit cube now spans x from 0 to 1 and z from -5 to -7
+        let aabb = instance.bounding_box();
+        assert!(aabb.min.abs_diff_eq(Vec3::new(0., 0., -7.), 1e-5));
+        assert!(aabb.max.abs_diff_eq(Vec3::new(1., 1., -5.), 1e-5));
+
+        let ray = Ray::new(Vec3::new(0.5, 0.5, 0.), Vec3::NEG_Z);
+        let hit = instance.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 5.).abs() < 1e-5);
+        assert!(hit.point.abs_diff_eq(Vec3::new(0.5, 0.5, -5.), 1e-5));
+        assert!(hit.normal.abs_diff_eq(Vec3::Z, 1e-5));
+        assert!(hit.front_face);
+
+        let ray = Ray::new(Vec3::new(5., 0.5, -6.), Vec3::NEG_X);
+        let hit = instance.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 4.).abs() < 1e-5);
+        assert!(hit.normal.abs_diff_eq(Vec3::X, 1e-5));
+
+        assert!(!instance.is_light());
+    }
+
+    #[test]
+    fn normals_of_stretched_spheres() {
+        let sphere = Arc::new(Sphere::new(Vec3::ZERO, 1., Metal::new(BLACK)));
+        let instance = Instance::new(sphere, Affine3A::from_scale(Vec3::new(2., 1., 1.)));
+
+        // On the ellipse x²/4 + y² = 1 the normal is along (x/4, y)
+        let point = Vec3::new(2_f32.sqrt(), 0.5_f32.sqrt(), 0.);
+        let normal = Vec3::new(point.x / 4., point.y, 0.).normalize();
+        let ray = Ray::new(point + normal, -normal);
+        let hit = instance.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!(hit.point.abs_diff_eq(point, 1e-5));
+        assert!(hit.normal.abs_diff_eq(normal, 1e-5));
+    }
+
+    #[test]
+    fn moving_spheres_follow_the_time() {
+        let sphere = Arc::new(Sphere::new(Vec3::ZERO, 0.5, Metal::new(BLACK)));
+        let instance = Instance::moving(
+            sphere,
+            Affine3A::from_translation(Vec3::new(0., 0., -3.)),
+            Affine3A::from_translation(Vec3::new(2., 0., -3.)),
+        );
+
+        let aabb = instance.bounding_box();
+        assert!(aabb.min.abs_diff_eq(Vec3::new(-0.5, -0.5, -3.5), 1e-3));
+        assert!(aabb.max.abs_diff_eq(Vec3::new(2.5, 0.5, -2.5), 1e-3));
+
+        let ray = |x: f32, time: f32| Ray::new(Vec3::new(x, 0., 0.), Vec3::NEG_Z).with_time(time);
+        assert!(instance.hit(&ray(0., 0.), 0.001, f32::INFINITY).is_some());
+        assert!(instance.hit(&ray(0., 1.), 0.001, f32::INFINITY).is_none());
+        assert!(instance.hit(&ray(1., 0.5), 0.001, f32::INFINITY).is_some());
+        assert!(instance.hit(&ray(2., 1.), 0.001, f32::INFINITY).is_some());
+        // Still after the end of the motion
+        assert!(instance.hit(&ray(2., 3.), 0.001, f32::INFINITY).is_some());
+    }
+
+    #[test]
+    fn rotating_boxes_stay_in_their_bounds() {
+        let cube = Arc::new(Cuboid::new(Vec3::splat(-1.), Vec3::ONE, Metal::new(BLACK)));
+        let instance = Instance::moving(
+            cube,
+            Affine3A::IDENTITY,
+            Affine3A::from_rotation_y(90_f32.to_radians()),
+        );
+
+        // Half way the corners reach sqrt(2) along x and z
+        let aabb = instance.bounding_box();
+        assert!(aabb.max.x >= 2_f32.sqrt() && aabb.max.z >= 2_f32.sqrt());
+        assert!(aabb.max.x < 1.5 && aabb.max.y < 1.01);
+
+        let ray = Ray::new(Vec3::new(0., 0., 5.), Vec3::NEG_Z).with_time(0.5);
+        let hit = instance.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - (5. - 2_f32.sqrt())).abs() < 1e-4);
+    }
+
+    #[test]
+    fn lights_keep_their_density() {
+        let lamp = Arc::new(Sphere::new(Vec3::ZERO, 1., DiffuseLight::new(BLACK, 1.)));
+
+        let moved = Instance::new(
+            lamp.clone(),
+            Affine3A::from_scale_rotation_translation(
+                Vec3::splat(2.),
+                Quat::from_rotation_x(1.),
+                Vec3::new(0., 0., -10.),
+            ),
+        );
+        let reference = Sphere::new(Vec3::new(0., 0., -10.), 2., DiffuseLight::new(BLACK, 1.));
+        assert!(moved.is_light());
+
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(0.1, 0., -1.));
+        assert!((moved.pdf(&ray) - reference.pdf(&ray)).abs() < 1e-3 * reference.pdf(&ray));
+
+        let direction = moved.sample(Vec3::ZERO).unwrap();
+        assert!(moved.pdf(&Ray::new(Vec3::ZERO, direction)) > 0.);
+
+        let stretched = Instance::new(lamp, Affine3A::from_scale(Vec3::new(2., 1., 1.)));
+        assert!(!stretched.is_light());
+    }
+}
@@ -2,9 +2,22 @@ use std::sync::Arc;
 
 use glam::Vec3;
 
-use super::{materials::Material, ray::Ray};
+use super::{aabb::Aabb, materials::Material, ray::Ray};
 
+pub mod bvh;
+pub mod cone;
+pub mod constant_medium;
+pub mod cuboid;
+pub mod cylinder;
+pub mod disk;
+pub mod frame;
+pub mod instance;
+pub mod plane;
+pub mod quad;
 pub mod sphere;
+pub mod torus;
+pub mod triangle;
+pub mod triangle_mesh;
 
 #[derive(Debug, Clone)]
 pub struct Hit {
@@ -12,9 +25,71 @@ pub struct Hit {
     pub point: Vec3,
     pub normal: Vec3,
     pub front_face: bool,
+    /// Texture coordinates, from 0 to 1 on bounded surfaces
+    pub u: f32,
+    pub v: f32,
     pub material: Arc<dyn Material>,
 }
 
+impl Hit {
+    /// Hit at `distance` along `ray`, the normal is flipped to face the ray when the back is hit
+    pub fn new(
+        ray: &Ray,
+        distance: f32,
+        outward_normal: Vec3,
+        (u, v): (f32, f32),
+        material: &Arc<dyn Material>,
+    ) -> Hit {
+        let front_face = ray.direction.dot(outward_normal) < 0.;
+
+        Hit {
+            distance,
+            point: ray.at(distance),
+            normal: if front_face {
+                outward_normal
+            } else {
+                -outward_normal
+            },
+            front_face,
+            u,
+            v,
+            material: material.clone(),
+        }
+    }
+}
+
 pub trait Mesh: 'static + Sync + Send {
     fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit>;
+
+    /// Box containing the whole mesh, [`Aabb::INFINITE`] when it is unbounded
+    fn bounding_box(&self) -> Aabb;
+
+    /// Whether the mesh is made of an emissive material and can be sampled as a light
+    fn is_light(&self) -> bool {
+        false
+    }
+
+    /// Direction from `origin` towards a random point of the surface, following [`Mesh::pdf`].
+    /// `None` when the mesh cannot be sampled
+    fn sample(&self, _origin: Vec3) -> Option<Vec3> {
+        None
+    }
+
+    /// Density, in solid angle, of [`Mesh::sample`] returning the direction of `ray`
+    fn pdf(&self, _ray: &Ray) -> f32 {
+        0.
+    }
+}
+
+/// Converts the density of a point picked uniformly on a surface of `area` to the
+/// density of the direction of `ray`, which hit that surface in `hit`
+pub fn area_to_solid_angle(ray: &Ray, hit: &Hit, area: f32) -> f32 {
+    let direction = ray.direction.normalize();
+    let cosine = hit.normal.dot(direction).abs();
+    if cosine < 1e-8 {
+        return 0.;
+    }
+
+    let distance_squared = hit.distance * hit.distance * ray.direction.length_squared();
+    distance_squared / (cosine * area)
 }
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use glam::Vec3;
+
+use crate::utils::{aabb::Aabb, materials::Material, ray::Ray};
+
+use super::{frame::Frame, Hit, Mesh};
+
+/// Infinite plane through `point`, the front face is the side `normal` points to.
+///
+/// The texture coordinates are distances along the plane, so textures repeat every unit.
+#[derive(Debug, Clone)]
+pub struct Plane {
+    frame: Frame,
+    material: Arc<dyn Material>,
+}
+
+impl Plane {
+    pub fn new(point: Vec3, normal: Vec3, material: Arc<dyn Material>) -> Plane {
+        Plane {
+            frame: Frame::new(point, normal),
+            material,
+        }
+    }
+}
+
+impl Mesh for Plane {
+    fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit> {
+        let local = self.frame.to_local(ray);
+
+        if local.direction.y.abs() < 1e-8 {
+            return None;
+        }
+
+        let distance = -local.origin.y / local.direction.y;
+        if distance <= ray_t_min || ray_t_max <= distance {
+            return None;
+        }
+
+        let point = local.at(distance);
+
+        Some(Hit::new(
+            ray,
+            distance,
+            self.frame.axis(),
+            (point.x, point.z),
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::INFINITE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::Plane;
+    use crate::utils::{colors::BLACK, materials::metal::Metal, meshes::Mesh, ray::Ray};
+
+    #[test]
+    fn hit_from_both_sides() {
+        let plane = Plane::new(Vec3::new(0., -1., 0.), Vec3::Y, Metal::new(BLACK));
+
+        let ray = Ray::new(Vec3::new(100., 1., -50.), Vec3::new(0., -1., 0.));
+        let hit = plane.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert_eq!(hit.distance, 2.);
+        assert!(hit.front_face);
+        assert_eq!(hit.normal, Vec3::Y);
+
+        let ray = Ray::new(Vec3::new(0., -3., 0.), Vec3::new(1., 1., 0.));
+        let hit = plane.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal, Vec3::NEG_Y);
+
+        let ray = Ray::new(Vec3::ZERO, Vec3::X);
+        assert!(plane.hit(&ray, 0.001, f32::INFINITY).is_none());
+        assert!(!plane.bounding_box().is_finite());
+    }
+}
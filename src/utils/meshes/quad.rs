@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use glam::Vec3;
+
+use crate::utils::{aabb::Aabb, materials::Material, ray::Ray};
+
+use super::{area_to_solid_angle, Hit, Mesh};
+
+/// Parallelogram spanning `corner`, `corner + u`, `corner + v` and `corner + u + v`.
+///
+/// The front face is the side `u × v` points to.
+#[derive(Debug, Clone)]
+pub struct Quad {
+    corner: Vec3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    /// `n / (n · n)` with `n = u × v`, projects a point of the plane on `u` and `v`
+    w: Vec3,
+    material: Arc<dyn Material>,
+}
+
+impl Quad {
+    pub fn new(corner: Vec3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Quad {
+        let n = u.cross(v);
+
+        Quad {
+            corner,
+            u,
+            v,
+            normal: n.normalize(),
+            w: n / n.length_squared(),
+            material,
+        }
+    }
+
+    pub fn corners(&self) -> [Vec3; 4] {
+        [
+            self.corner,
+            self.corner + self.u,
+            self.corner + self.u + self.v,
+            self.corner + self.v,
+        ]
+    }
+
+    pub fn area(&self) -> f32 {
+        self.u.cross(self.v).length()
+    }
+}
+
+impl Mesh for Quad {
+    fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit> {
+        let denominator = self.normal.dot(ray.direction);
+
+        // Parallel to the plane
+        if denominator.abs() < 1e-8 {
+            return None;
+        }
+
+        let distance = self.normal.dot(self.corner - ray.origin) / denominator;
+        if distance <= ray_t_min || ray_t_max <= distance {
+            return None;
+        }
+
+        let point = ray.at(distance);
+        let planar = point - self.corner;
+        let alpha = self.w.dot(planar.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar));
+
+        if !(0. ..=1.).contains(&alpha) || !(0. ..=1.).contains(&beta) {
+            return None;
+        }
+
+        let front_face = denominator < 0.;
+
+        Some(Hit {
+            distance,
+            point,
+            normal: if front_face {
+                self.normal
+            } else {
+                -self.normal
+            },
+            front_face,
+            u: alpha,
+            v: beta,
+            material: self.material.clone(),
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // Axis aligned quads are flat, the padding keeps the slab test well defined
+        let aabb = Aabb::from_points(self.corners());
+        Aabb::new(aabb.min - 1e-4, aabb.max + 1e-4)
+    }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn sample(&self, origin: Vec3) -> Option<Vec3> {
+        let point = self.corner + rand::random::<f32>() * self.u + rand::random::<f32>() * self.v;
+        Some(point - origin)
+    }
+
+    fn pdf(&self, ray: &Ray) -> f32 {
+        match self.hit(ray, 0.001, f32::INFINITY) {
+            Some(hit) => area_to_solid_angle(ray, &hit, self.area()),
+            None => 0.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::Quad;
+    use crate::utils::{colors::BLACK, materials::metal::Metal, meshes::Mesh, ray::Ray};
+
+    fn quad() -> Quad {
+        Quad::new(
+            Vec3::new(-1., -1., -1.),
+            Vec3::new(2., 0., 0.),
+            Vec3::new(0., 2., 0.),
+            Metal::new(BLACK),
+        )
+    }
+
+    #[test]
+    fn hit_front_and_back() {
+        let q = quad();
+
+        let ray = Ray::new(Vec3::new(0.9, 0.9, 0.), Vec3::new(0., 0., -1.));
+        let hit = q.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 1.).abs() < 1e-6);
+        assert!(hit.front_face);
+        assert_eq!(hit.normal, Vec3::Z);
+        assert!((hit.u - 0.95).abs() < 1e-6 && (hit.v - 0.95).abs() < 1e-6);
+
+        let ray = Ray::new(Vec3::new(0., 0., -3.), Vec3::new(0., 0., 2.));
+        let hit = q.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 1.).abs() < 1e-6);
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal, Vec3::NEG_Z);
+    }
+
+    #[test]
+    fn misses() {
+        let q = quad();
+
+        let ray = Ray::new(Vec3::new(1.1, 0., 0.), Vec3::new(0., 0., -1.));
+        assert!(q.hit(&ray, 0.001, f32::INFINITY).is_none());
+
+        let ray = Ray::new(Vec3::new(0., 0., -1.), Vec3::X);
+        assert!(q.hit(&ray, 0.001, f32::INFINITY).is_none());
+
+        assert_eq!(q.area(), 4.);
+        assert!(q.bounding_box().hit(
+            Vec3::ZERO,
+            Vec3::new(0., 0., -1.).recip(),
+            0.,
+            f32::INFINITY
+        ));
+    }
+}
@@ -1,11 +1,11 @@
 use core::f32;
-use std::sync::Arc;
+use std::{f32::consts::PI, sync::Arc};
 
 use glam::Vec3;
 
-use crate::utils::{materials::Material, ray::Ray};
+use crate::utils::{aabb::Aabb, materials::Material, random_unit_vector, ray::Ray};
 
-use super::{Hit, Mesh};
+use super::{area_to_solid_angle, Hit, Mesh};
 
 #[derive(Debug, Clone)]
 pub struct Sphere {
@@ -51,6 +51,10 @@ impl Mesh for Sphere {
 
         let normal: Vec3 = (point - self.center) / self.radius;
 
+        // Longitude and latitude, starting from -X and from the bottom
+        let u = ((-normal.z).atan2(normal.x) + PI) / (2. * PI);
+        let v = (-normal.y).clamp(-1., 1.).acos() / PI;
+
         let front_face = ray.direction.dot(normal) < 0.;
 
         // Extremely expensive...
@@ -61,9 +65,48 @@ impl Mesh for Sphere {
             point,
             normal,
             front_face,
+            u,
+            v,
             material: self.material.clone(),
         })
     }
+
+    fn bounding_box(&self) -> Aabb {
+        let radius = Vec3::splat(self.radius.abs());
+        Aabb::new(self.center - radius, self.center + radius)
+    }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn sample(&self, origin: Vec3) -> Option<Vec3> {
+        let Some(cos_max) = self.cone_cosine(origin) else {
+            // From the inside the whole surface is visible
+            return Some(self.center + self.radius.abs() * random_unit_vector() - origin);
+        };
+
+        // Uniform within the cone of directions covered by the sphere
+        let cos_theta = 1. + rand::random::<f32>() * (cos_max - 1.);
+        let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+        let phi = 2. * PI * rand::random::<f32>();
+
+        let w = (self.center - origin).normalize();
+        let (u, v) = w.any_orthonormal_pair();
+
+        Some(sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * w)
+    }
+
+    fn pdf(&self, ray: &Ray) -> f32 {
+        let Some(hit) = self.hit(ray, 0.001, f32::INFINITY) else {
+            return 0.;
+        };
+
+        match self.cone_cosine(ray.origin) {
+            Some(cos_max) => 1. / (2. * PI * (1. - cos_max)),
+            None => area_to_solid_angle(ray, &hit, 4. * PI * self.radius * self.radius),
+        }
+    }
 }
 
 impl Sphere {
@@ -75,6 +118,15 @@ impl Sphere {
         }
     }
 
+    /// Cosine of the half angle of the cone the sphere covers seen from `origin`,
+    /// `None` from the inside
+    fn cone_cosine(&self, origin: Vec3) -> Option<f32> {
+        let distance_squared = (self.center - origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+
+        (distance_squared > radius_squared).then(|| (1. - radius_squared / distance_squared).sqrt())
+    }
+
     pub fn hit_naive2(&self, ray: &Ray) -> Option<f32> {
         let oc = self.center - ray.origin;
 
@@ -89,7 +141,7 @@ impl Sphere {
             return None;
         }
 
-        return Some((h - discriminant.sqrt()) / a);
+        Some((h - discriminant.sqrt()) / a)
     }
 
     #[cfg(test)]
@@ -108,7 +160,7 @@ impl Sphere {
             return None;
         }
 
-        return Some((-b - discriminant.sqrt()) / (2. * a));
+        Some((-b - discriminant.sqrt()) / (2. * a))
     }
 }
 
@@ -160,6 +212,27 @@ mod test {
         }
     }
 
+    #[test]
+    fn texture_coordinates() {
+        use crate::utils::{colors::BLACK, materials::metal::Metal};
+        use glam::Vec3;
+
+        let sphere = Sphere::new(Vec3::ZERO, 2., Metal::new(BLACK));
+        let uv = |origin: Vec3| {
+            let hit = sphere
+                .hit(&Ray::new(origin, -origin), 0.001, f32::INFINITY)
+                .unwrap();
+            (hit.u, hit.v)
+        };
+
+        // The seam is at -X, the poles are at v = 0 (bottom) and v = 1 (top)
+        assert_eq!(uv(Vec3::new(5., 0., 0.)), (0.5, 0.5));
+        assert_eq!(uv(Vec3::new(0., 0., 5.)), (0.25, 0.5));
+        assert_eq!(uv(Vec3::new(0., 0., -5.)), (0.75, 0.5));
+        assert_eq!(uv(Vec3::new(0., -5., 0.)).1, 0.);
+        assert_eq!(uv(Vec3::new(0., 5., 0.)).1, 1.);
+    }
+
     #[bench]
     fn hit(b: &mut Bencher) {
         let (spheres, rays) = test_examples();
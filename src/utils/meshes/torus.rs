@@ -0,0 +1,117 @@
+use std::{f32::consts::TAU, sync::Arc};
+
+use glam::Vec3;
+
+use crate::utils::{aabb::Aabb, materials::Material, ray::Ray, roots};
+
+use super::{frame::Frame, Hit, Mesh};
+
+/// Ring around `axis`, the tube of `minor` radius runs at `major` distance from `center`.
+///
+/// `u` goes around the axis and `v` around the tube, starting from its inner side.
+#[derive(Debug, Clone)]
+pub struct Torus {
+    frame: Frame,
+    major: f32,
+    minor: f32,
+    material: Arc<dyn Material>,
+}
+
+impl Torus {
+    pub fn new(
+        center: Vec3,
+        axis: Vec3,
+        major: f32,
+        minor: f32,
+        material: Arc<dyn Material>,
+    ) -> Torus {
+        Torus {
+            frame: Frame::new(center, axis),
+            major,
+            minor,
+            material,
+        }
+    }
+}
+
+impl Mesh for Torus {
+    fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit> {
+        let local = self.frame.to_local(ray);
+        let length = local.direction.length();
+
+        // The quartic loses too much precision in f32, and is simpler with a unit direction
+        let o = local.origin.as_dvec3();
+        let d = (local.direction / length).as_dvec3();
+        let (major, minor) = (self.major as f64, self.minor as f64);
+
+        // (|p|² + R² - r²)² = 4 R² (x² + z²)
+        let r2 = 4. * major * major;
+        let n = o.dot(d);
+        let k = o.length_squared() + major * major - minor * minor;
+        let coefficients = [
+            k * k - r2 * (o.x * o.x + o.z * o.z),
+            4. * n * k - 2. * r2 * (o.x * d.x + o.z * d.z),
+            4. * n * n + 2. * k - r2 * (d.x * d.x + d.z * d.z),
+            4. * n,
+            1.,
+        ];
+
+        let distance = roots::quartic(coefficients)
+            .into_iter()
+            .map(|t| t as f32 / length)
+            .filter(|t| ray_t_min < *t && *t < ray_t_max)
+            .min_by(f32::total_cmp)?;
+
+        let p = local.at(distance);
+        let ring = Vec3::new(p.x, 0., p.z).normalize_or(Vec3::X);
+        let normal = (p - ring * self.major) / self.minor;
+
+        let around_axis = p.z.atan2(p.x).rem_euclid(TAU);
+        let around_tube = p.y.atan2(p.x.hypot(p.z) - self.major);
+
+        Some(Hit::new(
+            ray,
+            distance,
+            self.frame.vector_to_world(normal),
+            (around_axis / TAU, around_tube / TAU + 0.5),
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let outer = self.major + self.minor;
+        let extent = Vec3::new(outer, self.minor, outer);
+        self.frame.bounding_box(Aabb::new(-extent, extent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::Torus;
+    use crate::utils::{colors::BLACK, materials::metal::Metal, meshes::Mesh, ray::Ray};
+
+    #[test]
+    fn hit_the_tube_not_the_hole() {
+        let torus = Torus::new(Vec3::new(0., 0., -5.), Vec3::Y, 1., 0.25, Metal::new(BLACK));
+
+        // Through the plane of the ring, the outer side is hit first
+        let ray = Ray::new(Vec3::ZERO, Vec3::NEG_Z);
+        let hit = torus.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 3.75).abs() < 1e-4);
+        assert!(hit.front_face);
+        assert!(hit.normal.abs_diff_eq(Vec3::Z, 1e-4));
+
+        // Down the axis through the hole
+        let ray = Ray::new(Vec3::new(0., 5., -5.), Vec3::NEG_Y);
+        assert!(torus.hit(&ray, 0.001, f32::INFINITY).is_none());
+
+        // Down onto the top of the tube, with a non unit direction
+        let ray = Ray::new(Vec3::new(1., 5., -5.), Vec3::new(0., -2., 0.));
+        let hit = torus.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 2.375).abs() < 1e-4);
+        assert!(hit.normal.abs_diff_eq(Vec3::Y, 1e-4));
+        assert!((hit.v - 0.75).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use glam::Vec3;
+
+use crate::utils::{aabb::Aabb, materials::Material, ray::Ray};
+
+use super::{area_to_solid_angle, Hit, Mesh};
+
+/// Möller–Trumbore ray/triangle intersection.
+///
+/// Returns the distance and the barycentric coordinates `(u, v)` of the hit,
+/// where the point is `(1 - u - v) * a + u * b + v * c`.
+pub fn intersect(
+    ray: &Ray,
+    [a, b, c]: [Vec3; 3],
+    ray_t_min: f32,
+    ray_t_max: f32,
+) -> Option<(f32, f32, f32)> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+
+    let p = ray.direction.cross(edge2);
+    let determinant = edge1.dot(p);
+
+    // Parallel to the plane, the tolerance scales with the size of the triangle
+    // so that tiny and huge triangles behave the same
+    let tolerance = f32::EPSILON * edge1.length_squared().max(edge2.length_squared());
+    if determinant.abs() <= tolerance * ray.direction.length() {
+        return None;
+    }
+
+    let inverse = 1. / determinant;
+    let s = ray.origin - a;
+
+    let u = s.dot(p) * inverse;
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(edge1);
+    let v = ray.direction.dot(q) * inverse;
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+
+    let distance = edge2.dot(q) * inverse;
+    if distance <= ray_t_min || ray_t_max <= distance {
+        return None;
+    }
+
+    Some((distance, u, v))
+}
+
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    vertices: [Vec3; 3],
+    normals: Option<[Vec3; 3]>,
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(a: Vec3, b: Vec3, c: Vec3, material: Arc<dyn Material>) -> Triangle {
+        Triangle {
+            vertices: [a, b, c],
+            normals: None,
+            material,
+        }
+    }
+
+    /// Smooth shaded triangle, the normals are interpolated across the face
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Triangle {
+        self.normals = Some(normals.map(|n| n.normalize()));
+        self
+    }
+
+    pub fn vertices(&self) -> [Vec3; 3] {
+        self.vertices
+    }
+
+    pub fn area(&self) -> f32 {
+        let [a, b, c] = self.vertices;
+        (b - a).cross(c - a).length() / 2.
+    }
+}
+
+/// Builds the hit for a triangle intersected at `distance` with barycentrics `(u, v)`,
+/// which are also used as texture coordinates.
+///
+/// `front_face` always comes from the winding of the triangle, the shading
+/// normal is then flipped to face the ray like for every other mesh.
+pub fn make_hit(
+    ray: &Ray,
+    [a, b, c]: [Vec3; 3],
+    normals: Option<[Vec3; 3]>,
+    (distance, u, v): (f32, f32, f32),
+    material: &Arc<dyn Material>,
+) -> Hit {
+    let geometric = (b - a).cross(c - a).normalize();
+    let front_face = ray.direction.dot(geometric) < 0.;
+
+    let normal = match normals {
+        Some([na, nb, nc]) => ((1. - u - v) * na + u * nb + v * nc)
+            .try_normalize()
+            .unwrap_or(geometric),
+        None => geometric,
+    };
+
+    let normal = if front_face { normal } else { -normal };
+
+    Hit {
+        distance,
+        point: ray.at(distance),
+        normal,
+        front_face,
+        u,
+        v,
+        material: material.clone(),
+    }
+}
+
+impl Mesh for Triangle {
+    fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit> {
+        let intersection = intersect(ray, self.vertices, ray_t_min, ray_t_max)?;
+
+        Some(make_hit(
+            ray,
+            self.vertices,
+            self.normals,
+            intersection,
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(self.vertices)
+    }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn sample(&self, origin: Vec3) -> Option<Vec3> {
+        // Uniform over the area
+        let [a, b, c] = self.vertices;
+        let r1 = rand::random::<f32>().sqrt();
+        let r2 = rand::random::<f32>();
+
+        let point = (1. - r1) * a + r1 * (1. - r2) * b + r1 * r2 * c;
+        Some(point - origin)
+    }
+
+    fn pdf(&self, ray: &Ray) -> f32 {
+        match self.hit(ray, 0.001, f32::INFINITY) {
+            Some(hit) => area_to_solid_angle(ray, &hit, self.area()),
+            None => 0.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::Triangle;
+    use crate::utils::{colors::BLACK, materials::metal::Metal, meshes::Mesh, ray::Ray};
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            Vec3::new(-1., -1., -1.),
+            Vec3::new(1., -1., -1.),
+            Vec3::new(0., 1., -1.),
+            Metal::new(BLACK),
+        )
+    }
+
+    #[test]
+    fn hit_front_face() {
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(0., 0., -1.));
+        let hit = triangle().hit(&ray, 0.001, f32::INFINITY).unwrap();
+
+        assert!((hit.distance - 1.).abs() < 1e-6);
+        assert!(hit.front_face);
+        assert_eq!(hit.normal, Vec3::new(0., 0., 1.));
+    }
+
+    #[test]
+    fn hit_back_face() {
+        let ray = Ray::new(Vec3::new(0., 0., -2.), Vec3::new(0., 0., 1.));
+        let hit = triangle().hit(&ray, 0.001, f32::INFINITY).unwrap();
+
+        assert!(!hit.front_face);
+        // The normal always points against the ray
+        assert_eq!(hit.normal, Vec3::new(0., 0., -1.));
+    }
+
+    #[test]
+    fn misses() {
+        let t = triangle();
+
+        // Outside of the edges
+        let ray = Ray::new(Vec3::new(2., 0., 0.), Vec3::new(0., 0., -1.));
+        assert!(t.hit(&ray, 0.001, f32::INFINITY).is_none());
+
+        // Parallel to the plane
+        let ray = Ray::new(Vec3::new(0., 0., -1.), Vec3::new(1., 0., 0.));
+        assert!(t.hit(&ray, 0.001, f32::INFINITY).is_none());
+
+        // Behind the ray and beyond t_max
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(0., 0., 1.));
+        assert!(t.hit(&ray, 0.001, f32::INFINITY).is_none());
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(0., 0., -1.));
+        assert!(t.hit(&ray, 0.001, 0.5).is_none());
+    }
+
+    #[test]
+    fn interpolated_normals() {
+        let t = triangle().with_normals([
+            Vec3::new(-1., 0., 1.),
+            Vec3::new(1., 0., 1.),
+            Vec3::new(0., 0., 1.),
+        ]);
+
+        let ray = Ray::new(Vec3::new(0.5, -0.5, 0.), Vec3::new(0., 0., -1.));
+        let hit = t.hit(&ray, 0.001, f32::INFINITY).unwrap();
+
+        assert!(hit.normal.x > 0.);
+        assert!((hit.normal.length() - 1.).abs() < 1e-6);
+    }
+}
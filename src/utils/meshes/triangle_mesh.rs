@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use anyhow::{bail, ensure};
+use glam::{Vec2, Vec3};
+
+use crate::utils::{aabb::Aabb, materials::Material, ray::Ray};
+
+use super::{
+    bvh::BvhTree,
+    triangle::{intersect, make_hit},
+    Hit, Mesh,
+};
+
+/// Indexed triangle mesh, the vertex attributes are shared between the faces
+#[derive(Debug, Clone)]
+pub struct TriangleMesh {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    indices: Vec<[u32; 3]>,
+    material: Arc<dyn Material>,
+    tree: BvhTree,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Vec3>,
+        indices: Vec<[u32; 3]>,
+        material: Arc<dyn Material>,
+    ) -> anyhow::Result<TriangleMesh> {
+        if let Some(face) = indices
+            .iter()
+            .position(|face| face.iter().any(|i| *i as usize >= positions.len()))
+        {
+            bail!(
+                "face {face} references vertex {:?}, but the mesh only has {} vertices",
+                indices[face],
+                positions.len()
+            );
+        }
+
+        let bounds = indices
+            .iter()
+            .map(|face| Aabb::from_points(face.map(|i| positions[i as usize])))
+            .collect::<Vec<_>>();
+
+        Ok(TriangleMesh {
+            tree: BvhTree::build(&bounds),
+            positions,
+            normals: vec![],
+            uvs: vec![],
+            indices,
+            material,
+        })
+    }
+
+    /// Per vertex normals, interpolated across each face
+    pub fn with_normals(mut self, normals: Vec<Vec3>) -> anyhow::Result<TriangleMesh> {
+        ensure!(
+            normals.len() == self.positions.len(),
+            "expected {} normals, got {}",
+            self.positions.len(),
+            normals.len()
+        );
+
+        self.normals = normals.into_iter().map(|n| n.normalize_or_zero()).collect();
+        Ok(self)
+    }
+
+    /// Per vertex texture coordinates
+    pub fn with_uvs(mut self, uvs: Vec<Vec2>) -> anyhow::Result<TriangleMesh> {
+        ensure!(
+            uvs.len() == self.positions.len(),
+            "expected {} uvs, got {}",
+            self.positions.len(),
+            uvs.len()
+        );
+
+        self.uvs = uvs;
+        Ok(self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn vertices(&self, face: usize) -> [Vec3; 3] {
+        self.indices[face].map(|i| self.positions[i as usize])
+    }
+
+    fn normals(&self, face: usize) -> Option<[Vec3; 3]> {
+        if self.normals.is_empty() {
+            return None;
+        }
+
+        Some(self.indices[face].map(|i| self.normals[i as usize]))
+    }
+
+    /// Texture coordinates at the barycentric coordinates `(u, v)` of a face
+    pub fn uv(&self, face: usize, u: f32, v: f32) -> Option<Vec2> {
+        if self.uvs.is_empty() {
+            return None;
+        }
+
+        let [a, b, c] = self.indices[face].map(|i| self.uvs[i as usize]);
+
+        Some((1. - u - v) * a + u * b + v * c)
+    }
+}
+
+impl Mesh for TriangleMesh {
+    fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit> {
+        let (face, intersection) =
+            self.tree
+                .traverse(ray, ray_t_min, ray_t_max, |face, distance| {
+                    intersect(ray, self.vertices(face), ray_t_min, distance)
+                        .map(|intersection| (intersection.0, (face, intersection)))
+                })?;
+
+        let mut hit = make_hit(
+            ray,
+            self.vertices(face),
+            self.normals(face),
+            intersection,
+            &self.material,
+        );
+
+        let (_, u, v) = intersection;
+        if let Some(uv) = self.uv(face, u, v) {
+            (hit.u, hit.v) = (uv.x, uv.y);
+        }
+
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.tree.bounds()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec2, Vec3};
+
+    use super::TriangleMesh;
+    use crate::utils::{colors::BLACK, materials::metal::Metal, meshes::Mesh, ray::Ray};
+
+    /// Two triangles forming a unit square at z = -1, plus a bigger one further away
+    fn mesh() -> TriangleMesh {
+        TriangleMesh::new(
+            vec![
+                Vec3::new(0., 0., -1.),
+                Vec3::new(1., 0., -1.),
+                Vec3::new(1., 1., -1.),
+                Vec3::new(0., 1., -1.),
+                Vec3::new(-5., -5., -3.),
+                Vec3::new(5., -5., -3.),
+                Vec3::new(0., 5., -3.),
+            ],
+            vec![[0, 1, 2], [0, 2, 3], [4, 5, 6]],
+            Metal::new(BLACK),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn closest_face_is_hit() {
+        let m = mesh();
+
+        let ray = Ray::new(Vec3::new(0.25, 0.75, 0.), Vec3::new(0., 0., -1.));
+        let hit = m.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 1.).abs() < 1e-6);
+        assert!(hit.front_face);
+
+        let ray = Ray::new(Vec3::new(-2., 0., 0.), Vec3::new(0., 0., -1.));
+        let hit = m.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 3.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn invalid_buffers_are_rejected() {
+        let material = Metal::new(BLACK);
+
+        assert!(TriangleMesh::new(vec![Vec3::ZERO; 2], vec![[0, 1, 2]], material.clone()).is_err());
+        assert!(mesh().with_normals(vec![Vec3::Z; 3]).is_err());
+        assert!(mesh().with_uvs(vec![Vec2::ZERO; 8]).is_err());
+    }
+
+    #[test]
+    fn uvs_are_interpolated() {
+        let m = mesh()
+            .with_uvs(vec![
+                Vec2::new(0., 0.),
+                Vec2::new(1., 0.),
+                Vec2::new(1., 1.),
+                Vec2::new(0., 1.),
+                Vec2::ZERO,
+                Vec2::ZERO,
+                Vec2::ZERO,
+            ])
+            .unwrap();
+
+        assert_eq!(m.uv(0, 0.5, 0.5), Some(Vec2::new(1., 0.5)));
+        assert_eq!(mesh().uv(0, 0.5, 0.5), None);
+    }
+}
@@ -1,11 +1,23 @@
 use glam::Vec3;
 
+pub mod aabb;
+pub mod aov;
+pub mod background;
 pub mod camera;
 pub mod colors;
+pub mod denoise;
+pub mod image;
 pub mod materials;
+pub mod meshes;
+pub mod obj;
 pub mod ray;
 pub mod ray_tracing;
-pub mod meshes;
+pub mod roots;
+pub mod scene;
+pub mod scene_file;
+pub mod textures;
+pub mod toml;
+pub mod tone_mapping;
 
 #[must_use]
 pub fn random_unit_vector() -> Vec3 {
@@ -19,8 +31,23 @@ pub fn random_unit_vector() -> Vec3 {
 
         let ls = p.length_squared();
 
-        if (1e-90 as f32) < ls && ls <= 1. {
+        if 1e-90_f32 < ls && ls <= 1. {
             return p / ls.sqrt();
         }
     }
 }
+
+#[must_use]
+pub fn random_in_unit_disk() -> Vec3 {
+    loop {
+        let p = Vec3::new(
+            rand::random_range(-1.0..1.),
+            rand::random_range(-1.0..1.),
+            0.,
+        );
+
+        if p.length_squared() < 1. {
+            return p;
+        }
+    }
+}
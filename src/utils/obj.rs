@@ -0,0 +1,490 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, bail, ensure, Context};
+use glam::{Vec2, Vec3};
+
+use super::{
+    image,
+    materials::{dielectric::Dielectric, lambertian::Lambertian, metal::Metal, Material},
+    meshes::{triangle_mesh::TriangleMesh, Mesh},
+    textures::image::ImageTexture,
+};
+
+/// Material as described in a `.mtl` file, colors are in the `0..=1` range
+#[derive(Debug, Clone, PartialEq)]
+pub struct MtlMaterial {
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+    pub dissolve: f32,
+    pub optical_density: f32,
+    pub illumination: u32,
+    /// Image replacing the diffuse color, mapped with the texture coordinates of the faces
+    pub diffuse_map: Option<PathBuf>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse: Vec3::splat(0.8),
+            specular: Vec3::ZERO,
+            shininess: 0.,
+            dissolve: 1.,
+            optical_density: 1.,
+            illumination: 2,
+            diffuse_map: None,
+        }
+    }
+}
+
+impl MtlMaterial {
+    /// Maps the phong-like parameters onto the closest material we can render
+    pub fn to_material(&self) -> anyhow::Result<Arc<dyn Material>> {
+        // illum 3 and above enable ray traced reflections
+        let reflective =
+            self.illumination >= 3 || self.specular.max_element() > self.diffuse.max_element();
+
+        // Transparent materials are treated as clear glass, partial opacity is not supported
+        Ok(if self.dissolve < 1. {
+            Dielectric::new(self.optical_density.max(1.))
+        } else if reflective && self.specular.max_element() > 0. {
+            // Blinn-Phong exponent to roughness, high exponents are sharp reflections
+            let roughness = (2. / (self.shininess.max(0.) + 2.)).sqrt();
+            Metal::fuzzy(self.specular * 255., roughness)
+        } else if let Some(path) = &self.diffuse_map {
+            Lambertian::textured(ImageTexture::new(image::read(path)?))
+        } else {
+            Lambertian::new(self.diffuse * 255.)
+        })
+    }
+}
+
+fn parse_floats<const N: usize>(args: &[&str], optional_from: usize) -> anyhow::Result<[f32; N]> {
+    ensure!(
+        (optional_from..=N).contains(&args.len()),
+        "expected {} values, got {}",
+        if optional_from == N {
+            N.to_string()
+        } else {
+            format!("{optional_from} to {N}")
+        },
+        args.len()
+    );
+
+    let mut values = [0.; N];
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = arg
+            .parse()
+            .map_err(|_| anyhow!("`{arg}` is not a number"))?;
+    }
+
+    Ok(values)
+}
+
+pub fn parse_mtl(source: &str) -> anyhow::Result<HashMap<String, MtlMaterial>> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (number, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args = tokens.collect::<Vec<_>>();
+
+        (|| {
+            if keyword == "newmtl" {
+                ensure!(!args.is_empty(), "missing material name");
+                if let Some((name, material)) = current.take() {
+                    materials.insert(name, material);
+                }
+                current = Some((args.join(" "), MtlMaterial::default()));
+                return Ok(());
+            }
+
+            let Some((_, material)) = current.as_mut() else {
+                bail!("`{keyword}` before any `newmtl`");
+            };
+
+            match keyword {
+                "Kd" => material.diffuse = Vec3::from_array(parse_floats::<3>(&args, 3)?),
+                "Ks" => material.specular = Vec3::from_array(parse_floats::<3>(&args, 3)?),
+                "Ns" => material.shininess = parse_floats::<1>(&args, 1)?[0],
+                "d" => material.dissolve = parse_floats::<1>(&args, 1)?[0],
+                "Tr" => material.dissolve = 1. - parse_floats::<1>(&args, 1)?[0],
+                "Ni" => material.optical_density = parse_floats::<1>(&args, 1)?[0],
+                // Options like `-s` come before the file name
+                "map_Kd" => {
+                    material.diffuse_map =
+                        Some(PathBuf::from(args.last().context("missing texture path")?))
+                }
+                "illum" => {
+                    material.illumination = args
+                        .first()
+                        .and_then(|a| a.parse().ok())
+                        .context("`illum` expects an integer")?
+                }
+                // Ambient, emission, other texture maps and the like are not supported
+                _ => {}
+            }
+
+            Ok(())
+        })()
+        .with_context(|| format!("line {}", number + 1))?;
+    }
+
+    if let Some((name, material)) = current {
+        materials.insert(name, material);
+    }
+
+    Ok(materials)
+}
+
+pub fn load_mtl(path: impl AsRef<Path>) -> anyhow::Result<HashMap<String, MtlMaterial>> {
+    let path = path.as_ref();
+    let source =
+        std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+
+    let mut materials = parse_mtl(&source)
+        .with_context(|| format!("invalid material library {}", path.display()))?;
+
+    // Texture maps are relative to the library
+    let directory = path.parent().unwrap_or(Path::new(""));
+    for material in materials.values_mut() {
+        if let Some(map) = material.diffuse_map.take() {
+            material.diffuse_map = Some(directory.join(map));
+        }
+    }
+
+    Ok(materials)
+}
+
+/// Resolves a 1-based, possibly negative (relative to the end), obj index
+fn resolve_index(index: &str, count: usize, kind: &str) -> anyhow::Result<usize> {
+    let parsed: i64 = index
+        .parse()
+        .map_err(|_| anyhow!("`{index}` is not a valid {kind} index"))?;
+
+    let resolved = match parsed {
+        0 => bail!("{kind} indices start at 1"),
+        i if i > 0 => i - 1,
+        i => count as i64 + i,
+    };
+
+    ensure!(
+        0 <= resolved && (resolved as usize) < count,
+        "{kind} index {index} is out of range, only {count} are defined"
+    );
+
+    Ok(resolved as usize)
+}
+
+/// Faces sharing the same group and material, turned into a single mesh
+#[derive(Default)]
+struct MeshBuilder {
+    positions: Vec<Vec3>,
+    normals: Vec<Option<Vec3>>,
+    uvs: Vec<Option<Vec2>>,
+    indices: Vec<[u32; 3]>,
+    // obj vertices reference each attribute separately, meshes share one index
+    remap: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+}
+
+impl MeshBuilder {
+    fn vertex(&mut self, key: (usize, Option<usize>, Option<usize>), obj: &ObjData) -> u32 {
+        *self.remap.entry(key).or_insert_with(|| {
+            self.positions.push(obj.positions[key.0]);
+            self.uvs.push(key.1.map(|i| obj.uvs[i]));
+            self.normals.push(key.2.map(|i| obj.normals[i]));
+            self.positions.len() as u32 - 1
+        })
+    }
+
+    fn build(self, material: Arc<dyn Material>) -> anyhow::Result<TriangleMesh> {
+        let mut mesh = TriangleMesh::new(self.positions, self.indices, material)?;
+
+        // Attributes are only kept when every vertex has them
+        if let Some(normals) = self.normals.into_iter().collect::<Option<Vec<_>>>() {
+            mesh = mesh.with_normals(normals)?;
+        }
+        if let Some(uvs) = self.uvs.into_iter().collect::<Option<Vec<_>>>() {
+            mesh = mesh.with_uvs(uvs)?;
+        }
+
+        Ok(mesh)
+    }
+}
+
+#[derive(Default)]
+struct ObjData {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+}
+
+/// Parses an obj file, `directory` is where `mtllib` files are looked up.
+///
+/// Faces are split into one mesh per group and material, polygons are fan triangulated.
+pub fn parse(
+    source: &str,
+    directory: &Path,
+    default_material: Arc<dyn Material>,
+) -> anyhow::Result<Vec<TriangleMesh>> {
+    let mut data = ObjData::default();
+    let mut library: HashMap<String, MtlMaterial> = HashMap::new();
+
+    let mut group = String::new();
+    let mut material_name: Option<String> = None;
+
+    // Keeps the order in which the meshes appear in the file
+    let mut keys: Vec<(String, Option<String>)> = vec![];
+    let mut builders: HashMap<(String, Option<String>), MeshBuilder> = HashMap::new();
+
+    for (number, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args = tokens.collect::<Vec<_>>();
+
+        (|| {
+            match keyword {
+                "v" => data
+                    .positions
+                    .push(Vec3::from_slice(&parse_floats::<4>(&args, 3)?[..3])),
+                "vn" => data
+                    .normals
+                    .push(Vec3::from_array(parse_floats::<3>(&args, 3)?)),
+                "vt" => data
+                    .uvs
+                    .push(Vec2::from_slice(&parse_floats::<3>(&args, 1)?[..2])),
+                "g" | "o" => group = args.join(" "),
+                "usemtl" => {
+                    ensure!(!args.is_empty(), "missing material name");
+                    let name = args.join(" ");
+                    ensure!(
+                        library.contains_key(&name),
+                        "material `{name}` is not defined in any loaded mtllib"
+                    );
+                    material_name = Some(name);
+                }
+                "mtllib" => {
+                    ensure!(!args.is_empty(), "missing material library path");
+                    for file in &args {
+                        library.extend(load_mtl(directory.join(file))?);
+                    }
+                }
+                "f" => {
+                    ensure!(
+                        args.len() >= 3,
+                        "a face needs at least 3 vertices, got {}",
+                        args.len()
+                    );
+
+                    let vertices = args
+                        .iter()
+                        .map(|vertex| {
+                            let mut parts = vertex.split('/');
+                            let position = resolve_index(
+                                parts.next().unwrap_or_default(),
+                                data.positions.len(),
+                                "vertex",
+                            )?;
+                            let uv = match parts.next() {
+                                None | Some("") => None,
+                                Some(i) => Some(resolve_index(i, data.uvs.len(), "texture")?),
+                            };
+                            let normal = match parts.next() {
+                                None | Some("") => None,
+                                Some(i) => Some(resolve_index(i, data.normals.len(), "normal")?),
+                            };
+                            ensure!(parts.next().is_none(), "invalid face vertex `{vertex}`");
+
+                            Ok((position, uv, normal))
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+
+                    let key = (group.clone(), material_name.clone());
+                    let builder = builders.entry(key.clone()).or_insert_with(|| {
+                        keys.push(key);
+                        MeshBuilder::default()
+                    });
+
+                    let indices = vertices
+                        .into_iter()
+                        .map(|v| builder.vertex(v, &data))
+                        .collect::<Vec<_>>();
+
+                    for i in 1..indices.len() - 1 {
+                        builder
+                            .indices
+                            .push([indices[0], indices[i], indices[i + 1]]);
+                    }
+                }
+                // Smoothing groups, lines, points, free-form geometry and the like are not rendered
+                _ => {}
+            }
+
+            Ok(())
+        })()
+        .with_context(|| format!("line {}", number + 1))?;
+    }
+
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+
+    keys.into_iter()
+        .map(|key| {
+            let material = match &key.1 {
+                Some(name) => match materials.get(name) {
+                    Some(material) => material.clone(),
+                    None => {
+                        let material = library[name]
+                            .to_material()
+                            .with_context(|| format!("material `{name}`"))?;
+                        materials.insert(name.clone(), material.clone());
+                        material
+                    }
+                },
+                None => default_material.clone(),
+            };
+
+            builders
+                .remove(&key)
+                .expect("every key has a builder")
+                .build(material)
+                .with_context(|| format!("group `{}`", key.0))
+        })
+        .collect()
+}
+
+/// Loads an obj file and its material libraries
+pub fn load(
+    path: impl AsRef<Path>,
+    default_material: Arc<dyn Material>,
+) -> anyhow::Result<Vec<Arc<dyn Mesh>>> {
+    let path = path.as_ref();
+    let source =
+        std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+
+    let directory = path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(PathBuf::new);
+
+    let meshes = parse(&source, &directory, default_material)
+        .with_context(|| format!("invalid obj file {}", path.display()))?;
+
+    Ok(meshes
+        .into_iter()
+        .map(|mesh| Arc::new(mesh) as Arc<dyn Mesh>)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use glam::Vec3;
+
+    use super::{parse, parse_mtl, MtlMaterial};
+    use crate::utils::{colors::BLACK, materials::lambertian::Lambertian, meshes::Mesh, ray::Ray};
+
+    const QUAD: &str = "
+# a unit quad split in two groups
+v 0 0 -1
+v 1 0 -1
+v 1 1 -1
+v 0 1 -1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+vp 0.5 0.5
+s off
+
+g first
+f 1/1/1 2/2/1 3/3/1 4/4/1
+g second
+f -4 -2 -1
+";
+
+    #[test]
+    fn polygons_are_triangulated_per_group() {
+        let meshes = parse(QUAD, Path::new("."), Lambertian::new(BLACK)).unwrap();
+
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(meshes[0].len(), 2);
+        assert_eq!(meshes[1].len(), 1);
+
+        assert!(meshes[0].uv(0, 0., 0.).is_some());
+        assert!(meshes[1].uv(0, 0., 0.).is_none());
+
+        let ray = Ray::new(Vec3::new(0.75, 0.25, 0.), Vec3::new(0., 0., -1.));
+        let hit = meshes[0].hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn errors_report_the_line() {
+        let error = parse("v 0 0 0\nv 1 0\n", Path::new("."), Lambertian::new(BLACK)).unwrap_err();
+        assert_eq!(
+            format!("{error:#}"),
+            "line 2: expected 3 to 4 values, got 2"
+        );
+
+        let error =
+            parse("v 0 0 0\nf 1 2 3\n", Path::new("."), Lambertian::new(BLACK)).unwrap_err();
+        assert_eq!(
+            format!("{error:#}"),
+            "line 2: vertex index 2 is out of range, only 1 are defined"
+        );
+
+        let error = parse("usemtl missing\n", Path::new("."), Lambertian::new(BLACK)).unwrap_err();
+        assert!(format!("{error:#}").starts_with("line 1: material `missing`"));
+    }
+
+    #[test]
+    fn mtl_values_are_parsed() {
+        let materials = parse_mtl(
+            "
+newmtl red
+Kd 1 0 0
+newmtl gold # comment
+Kd 0.1 0.1 0.1
+Ks 1 0.8 0.3
+Ns 200
+d 0.5
+Ni 1.5
+illum 3
+map_Kd -s 2 2 gold.png
+",
+        )
+        .unwrap();
+
+        assert_eq!(materials["red"].diffuse, Vec3::new(1., 0., 0.));
+        assert_eq!(
+            materials["gold"],
+            MtlMaterial {
+                diffuse: Vec3::splat(0.1),
+                specular: Vec3::new(1., 0.8, 0.3),
+                shininess: 200.,
+                dissolve: 0.5,
+                optical_density: 1.5,
+                illumination: 3,
+                diffuse_map: Some("gold.png".into()),
+            }
+        );
+
+        let error = parse_mtl("Kd 1 1 1").unwrap_err();
+        assert_eq!(format!("{error:#}"), "line 1: `Kd` before any `newmtl`");
+    }
+}
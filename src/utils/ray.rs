@@ -4,11 +4,23 @@ use glam::Vec3;
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
+    /// Instant the ray travels at, within the shutter interval of the camera
+    pub time: f32,
 }
 
 impl Ray {
     pub fn new(origin: Vec3, direction: Vec3) -> Ray {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            time: 0.,
+        }
+    }
+
+    #[must_use]
+    pub fn with_time(mut self, time: f32) -> Ray {
+        self.time = time;
+        self
     }
 
     pub fn at(&self, t: f32) -> Vec3 {
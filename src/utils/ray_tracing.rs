@@ -3,83 +3,237 @@ use std::sync::Arc;
 use glam::Vec3;
 
 use super::{
-    camera::Camera,
-    colors::{self, SKY_BLUE, WHITE},
-    meshes::{Hit, Mesh},
-    ray::Ray,
+    aov::FirstHit, camera::Camera, meshes::Mesh, random_in_unit_disk, ray::Ray, scene::Scene,
 };
 
-fn compute_color<const N: usize>(meshes: &[Arc<dyn Mesh>; N], r: &Ray, max_depth: usize) -> Vec3 {
-    if max_depth == 0 {
-        return colors::BLACK;
+/// Power heuristic, weight of a sample picked with density `pdf` when `other` could have picked it too
+fn mis_weight(pdf: f32, other: f32) -> f32 {
+    let (pdf, other) = (pdf * pdf, other * other);
+    if pdf + other == 0. {
+        return 0.;
     }
-    let mut _closest_mesh: Option<&_> = None;
-    let mut closest_hit: Option<Hit> = None;
-
-    for s in meshes {
-        let distance = closest_hit
-            .clone()
-            .map(|h| h.distance)
-            .unwrap_or(f32::INFINITY);
-
-        if let Some(hit) = s.hit(r, 0.001, distance) {
-            _closest_mesh = Some(s);
-            closest_hit = Some(hit);
+
+    pdf / (pdf + other)
+}
+
+/// Path tracing with next event estimation.
+///
+/// At every diffuse bounce a shadow ray is sent towards a random light, and the
+/// light found by the scattered ray is counted too. Both estimates are combined
+/// with multiple importance sampling, so small lights and glossy reflections of
+/// big lights are both cheap to render.
+///
+/// Volumes are hit at a random depth inside them, so shadow rays crossing fog are
+/// blocked as often as the fog would absorb the light they look for.
+fn compute_color(scene: &Scene, r: &Ray, max_depth: usize) -> Vec3 {
+    let mut color = Vec3::ZERO;
+    let mut throughput = Vec3::ONE;
+    let mut ray = Ray::new(r.origin, r.direction).with_time(r.time);
+
+    // Density of the bounce that produced `ray`, `None` when lights were not sampled
+    let mut bounce_pdf: Option<f32> = None;
+
+    for _ in 0..max_depth {
+        // Light found by the bounce, lights sampled at the previous hit could have found it too
+        let weight = |ray: &Ray| match bounce_pdf {
+            Some(pdf) => mis_weight(pdf, scene.light_pdf(ray)),
+            None => 1.,
+        };
+
+        let Some(hit) = scene.hit(&ray, 0.001, f32::INFINITY) else {
+            return color + throughput * weight(&ray) * scene.background().color(ray.direction);
+        };
+
+        let emitted = hit.material.emitted(&ray, &hit);
+        if emitted != Vec3::ZERO {
+            color += throughput * weight(&ray) * emitted;
         }
-    }
 
-    if let Some(hit) = closest_hit {
-        let (scattered, attenuation) = hit.material.scatter(r, &hit);
+        let Some(scatter) = hit.material.scatter(&ray, &hit) else {
+            return color;
+        };
 
-        return attenuation * compute_color(meshes, &scattered, max_depth - 1);
-    }
+        if scatter.pdf.is_some() {
+            if let Some(direction) = scene.sample_light(hit.point) {
+                let shadow = Ray::new(hit.point, direction).with_time(ray.time);
+
+                // Whatever is found first is what lights the point, occluders emit nothing
+                let incoming = match scene.hit(&shadow, 0.001, f32::INFINITY) {
+                    Some(light) => light.material.emitted(&shadow, &light),
+                    None => scene.background().color(direction),
+                };
+                let light_pdf = scene.light_pdf(&shadow);
 
-    let dir = r.direction.normalize();
+                if incoming != Vec3::ZERO && light_pdf > 0. {
+                    let bsdf = hit.material.eval(&ray, &hit, direction);
+                    let weight = mis_weight(light_pdf, hit.material.pdf(&ray, &hit, direction));
 
-    let a = (dir.y + 1.) / 2.;
+                    color += throughput * bsdf * incoming * weight / light_pdf;
+                }
+            }
+        }
 
-    let color = (1.0 - a) * WHITE + a * SKY_BLUE;
+        throughput *= scatter.attenuation;
+        bounce_pdf = scatter.pdf;
+        ray = scatter.ray;
+    }
 
     color
 }
 
 #[derive(Clone)]
-pub struct RayTracing<const N: usize> {
-    spheres: [Arc<dyn Mesh>; N],
+pub struct RayTracing {
+    scene: Arc<Scene>,
     max_depth: usize,
     samples: usize,
     camera: Camera,
 }
 
-impl<const N: usize> RayTracing<N> {
-    pub fn new(
-        spheres: [Arc<dyn Mesh>; N],
-        camera: Camera,
-        max_depth: usize,
-        samples: usize,
-    ) -> Self {
+impl RayTracing {
+    pub fn new(scene: Arc<Scene>, camera: Camera, max_depth: usize, samples: usize) -> Self {
         Self {
-            spheres,
+            scene,
             max_depth,
             samples,
             camera,
         }
     }
 
+    /// Ray through a random point of the pixel
+    fn camera_ray(&self, x: f32, y: f32) -> Ray {
+        let pixel = self.camera.upper_left
+            + ((x + rand::random_range(-0.5..0.5)) * self.camera.delta_u)
+            + ((y + rand::random_range(-0.5..0.5)) * self.camera.delta_v);
+
+        // Thin lens, rays start from a random point of the lens and converge on the focus plane
+        let origin = if self.camera.has_defocus_blur() {
+            let p = random_in_unit_disk();
+            self.camera.center + p.x * self.camera.defocus_disk_u + p.y * self.camera.defocus_disk_v
+        } else {
+            self.camera.center
+        };
+
+        Ray::new(origin, pixel - origin).with_time(self.camera.random_time())
+    }
+
     pub fn compute_pixel(&self, x: f32, y: f32) -> Vec3 {
         let mut color = Vec3::new(0., 0., 0.);
 
         for _ in 0..self.samples {
-            let d = self.camera.upper_left
-                + ((x + rand::random_range(-0.5..0.5)) * self.camera.delta_u)
-                + ((y + rand::random_range(-0.5..0.5)) * self.camera.delta_v)
-                - self.camera.center;
+            let r = self.camera_ray(x, y);
+            color += compute_color(&self.scene, &r, self.max_depth) / self.samples as f32;
+        }
+
+        color
+    }
+
+    /// What a single ray through the pixel hits, for the output variables
+    pub fn first_hit(&self, x: f32, y: f32) -> FirstHit {
+        let ray = self.camera_ray(x, y);
 
-            let r = Ray::new(self.camera.center, d);
+        let Some((index, hit)) = self.scene.hit_object(&ray, 0.001, f32::INFINITY) else {
+            return FirstHit::BACKGROUND;
+        };
 
-            color += compute_color(&self.spheres, &r, self.max_depth) / self.samples as f32;
+        FirstHit {
+            albedo: hit.material.albedo(&hit),
+            normal: hit.normal,
+            depth: hit.distance * ray.direction.length(),
+            material_id: self.scene.material_id(&hit.material).unwrap_or(0),
+            object_id: index + 1,
         }
+    }
+}
 
-        color
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use glam::Vec3;
+
+    use super::RayTracing;
+    use crate::utils::{
+        aov::FirstHit,
+        camera::Camera,
+        materials::{diffuse_light::DiffuseLight, lambertian::Lambertian, Material, Scatter},
+        meshes::{sphere::Sphere, Hit},
+        ray::Ray,
+        scene::Scene,
+    };
+
+    /// Glows and absorbs everything
+    #[derive(Debug)]
+    struct Glow(Vec3);
+
+    impl Material for Glow {
+        fn scatter(&self, _ray: &Ray, _hit: &Hit) -> Option<Scatter> {
+            None
+        }
+
+        fn emitted(&self, _ray: &Ray, _hit: &Hit) -> Vec3 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn absorbed_paths_only_bring_back_emission() {
+        let mut scene = Scene::new();
+        // The camera is inside the sphere, every ray hits it
+        scene.add(Arc::new(Sphere::new(
+            Vec3::ZERO,
+            10.,
+            Arc::new(Glow(Vec3::new(10., 20., 30.))) as Arc<dyn Material>,
+        )));
+
+        let rt = RayTracing::new(Arc::new(scene), Camera::new(Vec3::ZERO, 4, 4), 10, 3);
+
+        let color = rt.compute_pixel(1., 2.);
+        assert!(color.abs_diff_eq(Vec3::new(10., 20., 30.), 1e-4));
+    }
+
+    #[test]
+    fn light_sampling_converges_to_the_reflected_light() {
+        let mut scene = Scene::new();
+        // A grey ball inside a lamp, it sees the same light in every direction
+        scene.add(Arc::new(Sphere::new(
+            Vec3::ZERO,
+            10.,
+            DiffuseLight::new(Vec3::splat(100.), 1.),
+        )));
+        scene.add(Arc::new(Sphere::new(
+            Vec3::new(0., 0., -2.),
+            1.,
+            Lambertian::new(Vec3::splat(127.5)),
+        )));
+        scene.build();
+        assert_eq!(scene.lights().len(), 1);
+
+        let rt = RayTracing::new(Arc::new(scene), Camera::new(Vec3::ZERO, 100, 100), 10, 4000);
+
+        let color = rt.compute_pixel(50., 50.);
+        assert!(color.abs_diff_eq(Vec3::splat(50.), 1.), "{color}");
+    }
+
+    #[test]
+    fn first_hit_of_the_camera_ray() {
+        let mut scene = Scene::new();
+        let material = Lambertian::new(Vec3::splat(51.)) as Arc<dyn Material>;
+        scene.add(Arc::new(Sphere::new(
+            Vec3::new(0., 0., -3.),
+            1.,
+            material.clone(),
+        )));
+        scene.add_material(material);
+        scene.build();
+
+        let rt = RayTracing::new(Arc::new(scene), Camera::new(Vec3::ZERO, 101, 101), 10, 1);
+
+        let hit = rt.first_hit(50., 50.);
+        assert!(hit.albedo.abs_diff_eq(Vec3::splat(0.2), 1e-6));
+        assert!(hit.normal.abs_diff_eq(Vec3::Z, 5e-2), "{}", hit.normal);
+        assert!((hit.depth - 2.).abs() < 1e-2, "{}", hit.depth);
+        assert_eq!((hit.material_id, hit.object_id), (1, 1));
+
+        assert_eq!(rt.first_hit(0., 0.), FirstHit::BACKGROUND);
     }
 }
@@ -0,0 +1,173 @@
+//! Real roots of the polynomials the analytic primitives are made of
+
+use std::f64::consts::PI;
+
+/// Below this, coefficients are considered zero
+const EPSILON: f64 = 1e-9;
+
+/// Roots of `a x² + b x + c`, smallest first
+pub fn quadratic(a: f32, b: f32, c: f32) -> Option<(f32, f32)> {
+    if a.abs() < f32::EPSILON {
+        if b == 0. {
+            return None;
+        }
+        let x = -c / b;
+        return Some((x, x));
+    }
+
+    let discriminant = b * b - 4. * a * c;
+    if discriminant < 0. {
+        return None;
+    }
+
+    // Avoids the cancellation of `-b + sqrt(d)` when `b` is large
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (x0, x1) = if q == 0. { (0., 0.) } else { (q / a, c / q) };
+
+    Some((x0.min(x1), x0.max(x1)))
+}
+
+/// Roots of the monic `x² + p x + q`
+fn monic_quadratic(p: f64, q: f64, roots: &mut Vec<f64>) {
+    let discriminant = p * p / 4. - q;
+
+    if discriminant.abs() < EPSILON {
+        roots.push(-p / 2.);
+    } else if discriminant > 0. {
+        let sqrt = discriminant.sqrt();
+        roots.extend([-p / 2. - sqrt, -p / 2. + sqrt]);
+    }
+}
+
+/// Roots of the monic `x³ + a x² + b x + c`, with Cardano's method
+fn monic_cubic(a: f64, b: f64, c: f64, roots: &mut Vec<f64>) {
+    // x = y - a / 3 gives y³ + 3 p y + 2 q
+    let p = (b - a * a / 3.) / 3.;
+    let q = (2. / 27. * a * a * a - a * b / 3. + c) / 2.;
+
+    let discriminant = q * q + p * p * p;
+    let start = roots.len();
+
+    if discriminant.abs() < EPSILON {
+        if q.abs() < EPSILON {
+            roots.push(0.);
+        } else {
+            let u = (-q).cbrt();
+            roots.extend([2. * u, -u]);
+        }
+    } else if discriminant < 0. {
+        // Three real roots
+        let phi = (-q / (-p * p * p).sqrt()).clamp(-1., 1.).acos() / 3.;
+        let t = 2. * (-p).sqrt();
+        roots.extend([
+            t * phi.cos(),
+            -t * (phi + PI / 3.).cos(),
+            -t * (phi - PI / 3.).cos(),
+        ]);
+    } else {
+        let sqrt = discriminant.sqrt();
+        roots.push((sqrt - q).cbrt() - (sqrt + q).cbrt());
+    }
+
+    roots[start..].iter_mut().for_each(|x| *x -= a / 3.);
+}
+
+/// Real roots of `c[4] x⁴ + c[3] x³ + c[2] x² + c[1] x + c[0]`, with Ferrari's method.
+///
+/// The roots are polished with a few Newton steps, the closed form alone loses
+/// too much precision for ray tracing.
+pub fn quartic(c: [f64; 5]) -> Vec<f64> {
+    let evaluate = |x: f64| (((c[4] * x + c[3]) * x + c[2]) * x + c[1]) * x + c[0];
+    let derivative = |x: f64| ((4. * c[4] * x + 3. * c[3]) * x + 2. * c[2]) * x + c[1];
+
+    let (a, b, cc, d) = (c[3] / c[4], c[2] / c[4], c[1] / c[4], c[0] / c[4]);
+
+    // x = y - a / 4 gives the depressed y⁴ + p y² + q y + r
+    let a2 = a * a;
+    let p = -3. / 8. * a2 + b;
+    let q = a2 * a / 8. - a * b / 2. + cc;
+    let r = -3. / 256. * a2 * a2 + a2 * b / 16. - a * cc / 4. + d;
+
+    let mut roots = Vec::with_capacity(4);
+
+    if r.abs() < EPSILON {
+        // y (y³ + p y + q) = 0
+        roots.push(0.);
+        monic_cubic(0., p, q, &mut roots);
+    } else {
+        // Any real root of the resolvent cubic splits the quartic in two quadratics
+        let mut resolvent = Vec::with_capacity(3);
+        monic_cubic(-p / 2., -r, r * p / 2. - q * q / 8., &mut resolvent);
+        let z = resolvent[0];
+
+        let u = z * z - r;
+        let v = 2. * z - p;
+        if u < -EPSILON || v < -EPSILON {
+            return roots;
+        }
+        let u = u.max(0.).sqrt();
+        let v = v.max(0.).sqrt();
+        let v = if q < 0. { -v } else { v };
+
+        monic_quadratic(v, z - u, &mut roots);
+        monic_quadratic(-v, z + u, &mut roots);
+    }
+
+    for x in roots.iter_mut() {
+        *x -= a / 4.;
+
+        for _ in 0..2 {
+            let slope = derivative(*x);
+            if slope.abs() > EPSILON {
+                *x -= evaluate(*x) / slope;
+            }
+        }
+    }
+
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{quadratic, quartic};
+
+    #[test]
+    fn quadratic_roots() {
+        assert_eq!(quadratic(1., -3., 2.), Some((1., 2.)));
+        assert_eq!(quadratic(1., 0., 1.), None);
+        assert_eq!(quadratic(0., 2., -4.), Some((2., 2.)));
+
+        // No cancellation with a large linear term
+        let (x0, _) = quadratic(1., 1e4, 1.).unwrap();
+        assert!((x0 + 1e4).abs() < 1.);
+        let (_, x1) = quadratic(1., 1e4, 1.).unwrap();
+        assert!((x1 + 1e-4).abs() < 1e-8);
+    }
+
+    #[test]
+    fn quartic_roots() {
+        let roots = |c| {
+            let mut r = quartic(c);
+            r.sort_by(f64::total_cmp);
+            r
+        };
+
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        let r = roots([24., -50., 35., -10., 1.]);
+        assert_eq!(r.len(), 4);
+        for (root, expected) in r.iter().zip([1., 2., 3., 4.]) {
+            assert!((root - expected).abs() < 1e-9, "{r:?}");
+        }
+
+        // (x² + 1)(x - 2)(x + 0.5)
+        let r = roots([-1., -1.5, 0., -1.5, 1.]);
+        assert_eq!(r.len(), 2);
+        assert!(
+            (r[0] + 0.5).abs() < 1e-9 && (r[1] - 2.).abs() < 1e-9,
+            "{r:?}"
+        );
+
+        // x⁴ + 1
+        assert!(roots([1., 0., 0., 0., 1.]).is_empty());
+    }
+}
@@ -0,0 +1,267 @@
+use std::sync::Arc;
+
+use glam::Vec3;
+
+use super::{
+    aabb::Aabb,
+    background::Background,
+    materials::Material,
+    meshes::{bvh::Bvh, Hit, Mesh},
+    ray::Ray,
+};
+
+/// Collection of meshes whose size is only known at runtime.
+///
+/// Hits are found with a linear search until [`Scene::build`] is called, which
+/// builds a BVH over the objects and collects the lights to sample.
+/// Adding or removing objects drops both again.
+#[derive(Default)]
+pub struct Scene {
+    objects: Vec<Arc<dyn Mesh>>,
+    bvh: Option<Bvh>,
+    lights: Vec<Arc<dyn Mesh>>,
+    background: Background,
+    /// Materials known by name, their position gives the material id
+    materials: Vec<Arc<dyn Material>>,
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Scene::default()
+    }
+
+    pub fn add(&mut self, mesh: Arc<dyn Mesh>) {
+        self.objects.push(mesh);
+        self.invalidate();
+    }
+
+    pub fn remove(&mut self, index: usize) -> Arc<dyn Mesh> {
+        self.invalidate();
+        self.objects.remove(index)
+    }
+
+    pub fn retain(&mut self, keep: impl FnMut(&Arc<dyn Mesh>) -> bool) {
+        self.objects.retain(keep);
+        self.invalidate();
+    }
+
+    pub fn clear(&mut self) {
+        self.objects.clear();
+        self.invalidate();
+    }
+
+    pub fn objects(&self) -> &[Arc<dyn Mesh>] {
+        &self.objects
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn background(&self) -> &Background {
+        &self.background
+    }
+
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+    }
+
+    /// Gives `material` the next material id, for the material id output variable
+    pub fn add_material(&mut self, material: Arc<dyn Material>) {
+        self.materials.push(material);
+    }
+
+    /// Id of a material given to [`Scene::add_material`], starting from 1
+    pub fn material_id(&self, material: &Arc<dyn Material>) -> Option<usize> {
+        self.materials
+            .iter()
+            .position(|m| Arc::ptr_eq(m, material))
+            .map(|index| index + 1)
+    }
+
+    /// Builds the acceleration structure, call it once all the objects are added
+    pub fn build(&mut self) {
+        self.bvh = Some(Bvh::new(self.objects.clone()));
+        self.lights = self
+            .objects
+            .iter()
+            .filter(|mesh| mesh.is_light())
+            .cloned()
+            .collect();
+    }
+
+    pub fn is_built(&self) -> bool {
+        self.bvh.is_some()
+    }
+
+    fn invalidate(&mut self) {
+        self.bvh = None;
+        self.lights.clear();
+    }
+
+    /// Lights found by [`Scene::build`]
+    pub fn lights(&self) -> &[Arc<dyn Mesh>] {
+        &self.lights
+    }
+
+    /// Lights and the background when it can be sampled
+    fn light_count(&self) -> usize {
+        self.lights.len() + usize::from(self.background.can_sample())
+    }
+
+    /// Direction from `origin` towards a random point of a random light,
+    /// an environment map counts as one more light
+    pub fn sample_light(&self, origin: Vec3) -> Option<Vec3> {
+        let count = self.light_count();
+        if count == 0 {
+            return None;
+        }
+
+        match self.lights.get(rand::random_range(0..count)) {
+            Some(light) => light.sample(origin),
+            None => self.background.sample(),
+        }
+    }
+
+    /// Density of [`Scene::sample_light`] returning the direction of `ray`
+    pub fn light_pdf(&self, ray: &Ray) -> f32 {
+        let count = self.light_count();
+        if count == 0 {
+            return 0.;
+        }
+
+        let lights = self.lights.iter().map(|light| light.pdf(ray)).sum::<f32>();
+        (lights + self.background.pdf(ray.direction)) / count as f32
+    }
+}
+
+impl Extend<Arc<dyn Mesh>> for Scene {
+    fn extend<T: IntoIterator<Item = Arc<dyn Mesh>>>(&mut self, iter: T) {
+        self.objects.extend(iter);
+        self.invalidate();
+    }
+}
+
+impl FromIterator<Arc<dyn Mesh>> for Scene {
+    fn from_iter<T: IntoIterator<Item = Arc<dyn Mesh>>>(iter: T) -> Self {
+        Scene {
+            objects: iter.into_iter().collect(),
+            ..Scene::default()
+        }
+    }
+}
+
+impl Scene {
+    /// Closest hit, along with the index of the object hit in [`Scene::objects`]
+    pub fn hit_object(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<(usize, Hit)> {
+        if let Some(bvh) = &self.bvh {
+            return bvh.hit_index(ray, ray_t_min, ray_t_max);
+        }
+
+        let mut closest_hit: Option<(usize, Hit)> = None;
+
+        for (index, mesh) in self.objects.iter().enumerate() {
+            let distance = closest_hit
+                .as_ref()
+                .map(|(_, h)| h.distance)
+                .unwrap_or(ray_t_max);
+
+            if let Some(hit) = mesh.hit(ray, ray_t_min, distance) {
+                closest_hit = Some((index, hit));
+            }
+        }
+
+        closest_hit
+    }
+}
+
+impl Mesh for Scene {
+    fn hit(&self, ray: &Ray, ray_t_min: f32, ray_t_max: f32) -> Option<Hit> {
+        self.hit_object(ray, ray_t_min, ray_t_max)
+            .map(|(_, hit)| hit)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.objects
+            .iter()
+            .fold(Aabb::EMPTY, |aabb, mesh| aabb.union(&mesh.bounding_box()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use glam::Vec3;
+
+    use super::Scene;
+    use crate::utils::{
+        colors::BLACK,
+        materials::metal::Metal,
+        meshes::{plane::Plane, sphere::Sphere, Mesh},
+        ray::Ray,
+    };
+
+    fn sphere(z: f32) -> Arc<dyn Mesh> {
+        Arc::new(Sphere::new(Vec3::new(0., 0., z), 0.5, Metal::new(BLACK)))
+    }
+
+    #[test]
+    fn add_remove_and_build() {
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(0., 0., -1.));
+        let distance = |scene: &Scene| scene.hit(&ray, 0.001, f32::INFINITY).map(|h| h.distance);
+
+        let mut scene = Scene::new();
+        assert_eq!(distance(&scene), None);
+
+        scene.add(sphere(-4.));
+        scene.add(sphere(-2.));
+        assert_eq!(distance(&scene), Some(1.5));
+
+        scene.build();
+        assert!(scene.is_built());
+        assert_eq!(distance(&scene), Some(1.5));
+
+        scene.remove(1);
+        assert!(!scene.is_built());
+        assert_eq!(scene.len(), 1);
+        assert_eq!(distance(&scene), Some(3.5));
+
+        scene.clear();
+        assert!(scene.is_empty());
+        assert_eq!(distance(&scene), None);
+    }
+
+    #[test]
+    fn objects_and_materials_are_identified() {
+        let mut scene = Scene::new();
+        scene.add(sphere(-4.));
+        scene.add(sphere(-2.));
+        scene.add(Arc::new(Plane::new(
+            Vec3::new(0., -1., 0.),
+            Vec3::Y,
+            Metal::new(BLACK),
+        )));
+
+        let forward = Ray::new(Vec3::ZERO, Vec3::new(0., 0., -1.));
+        let down = Ray::new(Vec3::ZERO, Vec3::new(0., -1., -1.));
+        for built in [false, true] {
+            if built {
+                scene.build();
+            }
+            let object = |ray: &Ray| scene.hit_object(ray, 0.001, f32::INFINITY).map(|(i, _)| i);
+            assert_eq!(object(&forward), Some(1));
+            assert_eq!(object(&down), Some(2));
+        }
+
+        let (_, hit) = scene.hit_object(&forward, 0.001, f32::INFINITY).unwrap();
+        assert_eq!(scene.material_id(&hit.material), None);
+        scene.add_material(Metal::new(BLACK));
+        scene.add_material(hit.material.clone());
+        assert_eq!(scene.material_id(&hit.material), Some(2));
+    }
+}
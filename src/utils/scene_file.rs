@@ -0,0 +1,1136 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{bail, Context};
+use glam::{Affine3A, EulerRot, Quat, Vec3};
+
+use super::{
+    background::{Background, Environment},
+    camera::CameraBuilder,
+    colors::{SKY_BLUE, WHITE},
+    image,
+    materials::{
+        dielectric::Dielectric,
+        diffuse_light::DiffuseLight,
+        isotropic::Isotropic,
+        lambertian::Lambertian,
+        metal::Metal,
+        principled::{Principled, PrincipledParams},
+        Material,
+    },
+    meshes::{
+        bvh::Bvh, cone::Cone, constant_medium::ConstantMedium, cuboid::Cuboid, cylinder::Cylinder,
+        disk::Disk, instance::Instance, plane::Plane, quad::Quad, sphere::Sphere, torus::Torus,
+        triangle::Triangle, Mesh,
+    },
+    obj,
+    scene::Scene,
+    textures::{checker::Checker, image::ImageTexture, noise::Noise, solid::SolidColor, Texture},
+    toml::{self, Section, Value},
+    tone_mapping::{ToneMapper, ToneMapping},
+};
+
+/// Everything needed to render a scene, as loaded from a scene file.
+///
+/// ```toml
+/// [camera]
+/// look_from = [0, 0, 0]
+/// look_at = [0, 0, -1]
+/// vup = [0, 1, 0]
+/// vfov = 90 # vertical field of view in degrees
+/// defocus_angle = 0 # depth of field, 0 disables it
+/// focus_distance = 1 # defaults to the distance to look_at
+/// shutter = [0, 1] # interval of time, objects are placed at 0 and moved by 1
+///
+/// [render]
+/// samples = 5
+/// max_depth = 100
+/// tone_mapping = "clamp" # or "reinhard" and "aces", which keep the highlights
+/// exposure = 0 # in stops, every one doubles the light
+/// denoise = false # smooths the noise of low sample counts away
+///
+/// [background]
+/// type = "gradient" # from `bottom` to `top`, or "solid" with a `color`, black by default
+/// bottom = [255, 255, 255]
+/// top = [127, 180, 255]
+/// # or "environment" with the `path` of a .hdr equirectangular image,
+/// # an `intensity` multiplier and a `rotation` in degrees around the vertical axis
+///
+/// [textures.tiles]
+/// type = "checker" # cubes of side `scale` alternating the `even` and `odd` colors
+/// scale = 0.5
+/// even = [255, 255, 255]
+/// odd = [0, 0, 0]
+/// # or "image" with the `path` of a .png or .ppm, "noise" with a `scale` and a `color`
+/// # for marble like veins, or "solid" with a `color`
+///
+/// [materials.ground]
+/// type = "lambertian" # or "metal", which also takes a `fuzz` from 0 to 1
+/// color = [42, 157, 143] # 0 to 255, or `texture = "tiles"`
+///
+/// [materials.glass]
+/// type = "dielectric"
+/// ior = 1.5 # index of refraction
+///
+/// [materials.lamp]
+/// type = "diffuse_light"
+/// color = [255, 255, 255]
+/// intensity = 4 # multiplies the color, defaults to 1
+///
+/// [[objects]]
+/// type = "sphere" # or "triangle" with `vertices`, "quad" with a `corner` and the
+///                 # edges `u` and `v`, "plane" with a `point` and a `normal`,
+///                 # "disk" with a `center`, `normal` and `radius`, "box" with
+///                 # opposite corners `min` and `max`, "cylinder" from `base` to
+///                 # `top` or "cone" from `base` to `apex` with a `radius`,
+///                 # "torus" with a `center`, an `axis` (y by default), a
+///                 # `major_radius` and `minor_radius`, or "mesh" with an obj `path`
+/// center = [0, -100.5, -1]
+/// radius = 100
+/// material = "ground"
+///
+/// [materials.car_paint]
+/// type = "principled" # physically based, every parameter goes from 0 to 1
+/// color = [180, 20, 20] # or `texture`
+/// metallic = 0
+/// roughness = 0.5
+/// specular = 0.5 # reflectance of non metals, 0.5 is the usual 4%
+/// clearcoat = 1 # glossy varnish on top
+/// sheen = 0 # soft rim at grazing angles, for cloth
+/// transmission = 0 # lets the light through, like glass
+/// ior = 1.5 # index of refraction of the transmitted light
+///
+/// [materials.smoke]
+/// type = "isotropic" # scatters in every direction, for volumes
+/// color = [200, 200, 200]
+///
+/// [[objects]]
+/// type = "sphere"
+/// center = [0, 1, -1]
+/// radius = 1
+/// material = "smoke"
+/// density = 0.5 # fills the object with fog instead of making a surface, closed objects only
+///
+/// [[objects]]
+/// type = "mesh" # the same file is loaded once, however many times it is placed
+/// path = "teapot.obj"
+/// # Any object can be moved, scaling first, then rotating in degrees around
+/// # x, y and z, and translating
+/// transform = { scale = 0.5, rotate = [0, 45, 0], translate = [1, 0, -2] }
+/// # Where the object is at time 1, it is blurred along the way while the
+/// # shutter of the camera is open
+/// motion = { scale = 0.5, rotate = [0, 90, 0], translate = [1, 0.5, -2] }
+/// ```
+pub struct SceneDescription {
+    pub camera: CameraBuilder,
+    pub samples: usize,
+    pub max_depth: usize,
+    pub tone_mapping: ToneMapping,
+    pub denoise: bool,
+    pub scene: Scene,
+}
+
+pub const DEFAULT_SAMPLES: usize = 5;
+pub const DEFAULT_MAX_DEPTH: usize = 100;
+
+/// Scene used when none is given on the command line
+pub const DEFAULT_SCENE: &str = include_str!("../../scenes/default.toml");
+
+pub fn default_scene() -> SceneDescription {
+    parse(DEFAULT_SCENE, Path::new(".")).expect("the default scene is valid")
+}
+
+pub fn load(path: impl AsRef<Path>) -> anyhow::Result<SceneDescription> {
+    let path = path.as_ref();
+    let source =
+        std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+
+    let directory = path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(PathBuf::new);
+
+    parse(&source, &directory).with_context(|| format!("invalid scene file {}", path.display()))
+}
+
+/// Parses a scene, `directory` is where relative mesh paths are looked up
+pub fn parse(source: &str, directory: &Path) -> anyhow::Result<SceneDescription> {
+    let document = toml::parse(source)?;
+
+    let root = Section {
+        path: "scene",
+        table: &document,
+    };
+    root.allow(&[
+        "camera",
+        "render",
+        "background",
+        "textures",
+        "materials",
+        "objects",
+    ])?;
+
+    let empty = Value::Table(toml::Table::new());
+
+    let camera = Section::new("camera", document.get("camera").unwrap_or(&empty))?;
+    camera.allow(&[
+        "look_from",
+        "look_at",
+        "vup",
+        "vfov",
+        "defocus_angle",
+        "focus_distance",
+        "shutter",
+    ])?;
+
+    let defaults = CameraBuilder::default();
+    let camera = CameraBuilder {
+        look_from: camera.optional("look_from")?.unwrap_or(defaults.look_from),
+        look_at: camera.optional("look_at")?.unwrap_or(defaults.look_at),
+        vup: camera.optional("vup")?.unwrap_or(defaults.vup),
+        vertical_fov: camera.optional("vfov")?.unwrap_or(defaults.vertical_fov),
+        defocus_angle: camera
+            .optional("defocus_angle")?
+            .unwrap_or(defaults.defocus_angle),
+        focus_distance: camera.optional("focus_distance")?,
+        shutter: camera
+            .optional::<[f32; 2]>("shutter")?
+            .map_or(defaults.shutter, |[open, close]| (open, close)),
+    };
+    camera.validate().context("camera")?;
+
+    let render = Section::new("render", document.get("render").unwrap_or(&empty))?;
+    render.allow(&[
+        "samples",
+        "max_depth",
+        "tone_mapping",
+        "exposure",
+        "denoise",
+    ])?;
+
+    let samples = render.optional("samples")?.unwrap_or(DEFAULT_SAMPLES);
+    if samples == 0 {
+        bail!("render.samples: must be greater than 0");
+    }
+
+    let tone_mapping = ToneMapping {
+        tone_mapper: match render.optional::<String>("tone_mapping")? {
+            Some(name) => name.parse().context("render.tone_mapping")?,
+            None => ToneMapper::default(),
+        },
+        exposure: render.optional("exposure")?.unwrap_or(0.),
+    };
+
+    let textures = parse_textures(document.get("textures").unwrap_or(&empty), directory)?;
+    let materials = parse_materials(document.get("materials").unwrap_or(&empty), &textures)?;
+
+    let background = parse_background(document.get("background"), directory)?;
+
+    let mut scene = match document.get("objects") {
+        None => Scene::new(),
+        Some(Value::Array(objects)) => parse_objects(objects, &materials, directory)?,
+        Some(other) => bail!(
+            "objects: expected an array of tables, found {}",
+            other.kind()
+        ),
+    };
+    scene.set_background(background);
+
+    // Sorted by name, so that material ids do not change between runs
+    let mut names = materials.keys().collect::<Vec<_>>();
+    names.sort();
+    for name in names {
+        scene.add_material(materials[name].clone());
+    }
+
+    Ok(SceneDescription {
+        camera,
+        samples,
+        max_depth: render.optional("max_depth")?.unwrap_or(DEFAULT_MAX_DEPTH),
+        tone_mapping,
+        denoise: render.optional("denoise")?.unwrap_or(false),
+        scene,
+    })
+}
+
+fn parse_background(value: Option<&Value>, directory: &Path) -> anyhow::Result<Background> {
+    let Some(value) = value else {
+        return Ok(Background::default());
+    };
+
+    let background = Section::new("background", value)?;
+
+    let kind: String = background.required("type")?;
+    Ok(match kind.as_str() {
+        "solid" => {
+            background.allow(&["type", "color"])?;
+            Background::Solid(background.optional("color")?.unwrap_or(Vec3::ZERO))
+        }
+        "gradient" => {
+            background.allow(&["type", "bottom", "top"])?;
+            Background::Gradient {
+                bottom: background.optional("bottom")?.unwrap_or(WHITE),
+                top: background.optional("top")?.unwrap_or(SKY_BLUE),
+            }
+        }
+        "environment" => {
+            background.allow(&["type", "path", "intensity", "rotation"])?;
+            let file: String = background.required("path")?;
+            let intensity: f32 = background.optional("intensity")?.unwrap_or(1.);
+            if intensity < 0. {
+                bail!("background.intensity: must not be negative");
+            }
+            let image = image::read(&directory.join(file)).context("background.path")?;
+            // Every direction must land on a pixel
+            if image.pixels.is_empty() {
+                bail!("background.path: the image is empty");
+            }
+            Background::Environment(Arc::new(Environment::new(
+                image,
+                intensity,
+                background.optional("rotation")?.unwrap_or(0.),
+            )))
+        }
+        other => bail!(
+            "background.type: unknown background `{other}`, expected solid, gradient or environment"
+        ),
+    })
+}
+
+fn parse_textures(
+    value: &Value,
+    directory: &Path,
+) -> anyhow::Result<HashMap<String, Arc<dyn Texture>>> {
+    let textures = Section::new("textures", value)?;
+
+    textures
+        .table
+        .iter()
+        .map(|(name, value)| {
+            let path = format!("textures.{name}");
+            let section = Section::new(&path, value)?;
+
+            let kind: String = section.required("type")?;
+            let texture: Arc<dyn Texture> = match kind.as_str() {
+                "solid" => {
+                    section.allow(&["type", "color"])?;
+                    SolidColor::new(section.required("color")?)
+                }
+                "checker" => {
+                    section.allow(&["type", "scale", "even", "odd"])?;
+                    let scale: f32 = section.required("scale")?;
+                    if scale <= 0. {
+                        bail!("{path}.scale: must be greater than 0");
+                    }
+                    Checker::colors(scale, section.required("even")?, section.required("odd")?)
+                }
+                "image" => {
+                    section.allow(&["type", "path"])?;
+                    let file: String = section.required("path")?;
+                    let image = image::read(&directory.join(file))
+                        .with_context(|| format!("{path}.path"))?;
+                    ImageTexture::new(image)
+                }
+                "noise" => {
+                    section.allow(&["type", "scale", "color"])?;
+                    Noise::new(
+                        section.optional("scale")?.unwrap_or(1.),
+                        section.optional("color")?.unwrap_or(Vec3::splat(255.)),
+                    )
+                }
+                other => bail!(
+                    "{path}.type: unknown texture type `{other}`, expected solid, checker, image or noise"
+                ),
+            };
+
+            Ok((name.clone(), texture))
+        })
+        .collect()
+}
+
+const MATERIAL_TYPES: &str =
+    "lambertian, metal, dielectric, diffuse_light, isotropic or principled";
+
+fn parse_materials(
+    value: &Value,
+    textures: &HashMap<String, Arc<dyn Texture>>,
+) -> anyhow::Result<HashMap<String, Arc<dyn Material>>> {
+    let materials = Section::new("materials", value)?;
+
+    materials
+        .table
+        .iter()
+        .map(|(name, value)| {
+            let path = format!("materials.{name}");
+            let section = Section::new(&path, value)?;
+
+            // Either a plain `color` or the name of a `texture`
+            let albedo = || -> anyhow::Result<Arc<dyn Texture>> {
+                let color: Option<Vec3> = section.optional("color")?;
+                let texture: Option<String> = section.optional("texture")?;
+
+                match (color, texture) {
+                    (Some(color), None) => Ok(SolidColor::new(color)),
+                    (None, Some(texture)) => textures
+                        .get(&texture)
+                        .cloned()
+                        .with_context(|| format!("{path}.texture: unknown texture `{texture}`")),
+                    (Some(_), Some(_)) => {
+                        bail!("{path}: expected either a color or a texture, not both")
+                    }
+                    (None, None) => bail!("{path}: missing `color` or `texture`"),
+                }
+            };
+
+            let kind: String = section.required("type")?;
+            let material: Arc<dyn Material> = match kind.as_str() {
+                "lambertian" => {
+                    section.allow(&["type", "color", "texture"])?;
+                    Lambertian::textured(albedo()?)
+                }
+                "metal" => {
+                    section.allow(&["type", "color", "texture", "fuzz"])?;
+                    let fuzz: f32 = section.optional("fuzz")?.unwrap_or(0.);
+                    if !(0. ..=1.).contains(&fuzz) {
+                        bail!("{path}.fuzz: must be between 0 and 1");
+                    }
+                    Metal::textured(albedo()?, fuzz)
+                }
+                "dielectric" => {
+                    section.allow(&["type", "ior"])?;
+                    let ior: f32 = section.required("ior")?;
+                    if ior <= 0. {
+                        bail!("{path}.ior: must be greater than 0");
+                    }
+                    Dielectric::new(ior)
+                }
+                "diffuse_light" => {
+                    section.allow(&["type", "color", "intensity"])?;
+                    let intensity: f32 = section.optional("intensity")?.unwrap_or(1.);
+                    if intensity < 0. {
+                        bail!("{path}.intensity: must not be negative");
+                    }
+                    DiffuseLight::new(section.required("color")?, intensity)
+                }
+                "isotropic" => {
+                    section.allow(&["type", "color", "texture"])?;
+                    Isotropic::textured(albedo()?)
+                }
+                "principled" => {
+                    section.allow(&[
+                        "type",
+                        "color",
+                        "texture",
+                        "metallic",
+                        "roughness",
+                        "specular",
+                        "clearcoat",
+                        "sheen",
+                        "transmission",
+                        "ior",
+                    ])?;
+                    let base_color = albedo()?;
+                    let mut params = PrincipledParams::default();
+                    for (key, parameter) in [
+                        ("metallic", &mut params.metallic),
+                        ("roughness", &mut params.roughness),
+                        ("specular", &mut params.specular),
+                        ("clearcoat", &mut params.clearcoat),
+                        ("sheen", &mut params.sheen),
+                        ("transmission", &mut params.transmission),
+                    ] {
+                        if let Some(value) = section.optional::<f32>(key)? {
+                            if !(0. ..=1.).contains(&value) {
+                                bail!("{path}.{key}: must be between 0 and 1");
+                            }
+                            *parameter = value;
+                        }
+                    }
+                    if let Some(ior) = section.optional::<f32>("ior")? {
+                        if ior <= 0. {
+                            bail!("{path}.ior: must be greater than 0");
+                        }
+                        params.ior = ior;
+                    }
+                    Principled::with_params(base_color, params)
+                }
+                other => {
+                    bail!("{path}.type: unknown material type `{other}`, expected {MATERIAL_TYPES}")
+                }
+            };
+
+            Ok((name.clone(), material))
+        })
+        .collect()
+}
+
+/// An optional transform of an object, scaling first, then rotating and translating
+fn parse_transform(section: &Section, key: &str) -> anyhow::Result<Option<Affine3A>> {
+    let Some(value) = section.table.get(key) else {
+        return Ok(None);
+    };
+
+    let path = format!("{}.{key}", section.path);
+    let transform = Section::new(&path, value)?;
+    transform.allow(&["translate", "rotate", "scale"])?;
+
+    let scale = match transform.table.get("scale") {
+        Some(Value::Array(_)) => transform.required("scale")?,
+        Some(_) => Vec3::splat(transform.required("scale")?),
+        None => Vec3::ONE,
+    };
+    if scale.cmpeq(Vec3::ZERO).any() {
+        bail!("{path}.scale: must not be zero");
+    }
+
+    let [x, y, z] = transform
+        .optional::<Vec3>("rotate")?
+        .unwrap_or(Vec3::ZERO)
+        .to_array()
+        .map(f32::to_radians);
+
+    Ok(Some(Affine3A::from_scale_rotation_translation(
+        scale,
+        // Around x first, then y and z
+        Quat::from_euler(EulerRot::ZYX, z, y, x),
+        transform.optional("translate")?.unwrap_or(Vec3::ZERO),
+    )))
+}
+
+const OBJECT_TYPES: &str =
+    "sphere, triangle, quad, plane, disk, box, cylinder, cone, torus or mesh";
+
+fn parse_objects(
+    objects: &[Value],
+    materials: &HashMap<String, Arc<dyn Material>>,
+    directory: &Path,
+) -> anyhow::Result<Scene> {
+    let mut scene = Scene::new();
+    // Meshes by file and default material
+    let mut loaded: HashMap<_, Vec<Arc<dyn Mesh>>> = HashMap::new();
+
+    for (index, value) in objects.iter().enumerate() {
+        let path = format!("objects[{index}]");
+        let section = Section::new(&path, value)?;
+
+        let material = |required: bool| -> anyhow::Result<Option<Arc<dyn Material>>> {
+            let name: Option<String> = if required {
+                Some(section.required("material")?)
+            } else {
+                section.optional("material")?
+            };
+
+            name.map(|name| {
+                materials
+                    .get(&name)
+                    .cloned()
+                    .with_context(|| format!("{path}.material: unknown material `{name}`"))
+            })
+            .transpose()
+        };
+
+        let positive = |key: &str| -> anyhow::Result<f32> {
+            let value: f32 = section.required(key)?;
+            if value <= 0. {
+                bail!("{path}.{key}: must be greater than 0");
+            }
+            Ok(value)
+        };
+
+        let direction = |key: &str| -> anyhow::Result<Vec3> {
+            let value: Vec3 = section.required(key)?;
+            if value.length_squared() == 0. {
+                bail!("{path}.{key}: must not be zero");
+            }
+            Ok(value)
+        };
+
+        // Axis from `from` to `to`, for the primitives with a base and a tip
+        let segment = |from: &str, to: &str| -> anyhow::Result<(Vec3, Vec3)> {
+            let (a, b): (Vec3, Vec3) = (section.required(from)?, section.required(to)?);
+            if a == b {
+                bail!("{path}: {from} and {to} must be different points");
+            }
+            Ok((a, b))
+        };
+
+        // Keys every object can have besides its own
+        let allow = |keys: &[&str]| {
+            section.allow(
+                &[
+                    &["type", "material", "transform", "motion", "density"],
+                    keys,
+                ]
+                .concat(),
+            )
+        };
+
+        // With a density the object is the boundary of a volume, filled with its material
+        let medium = match section.optional::<f32>("density")? {
+            Some(density) if density <= 0. => bail!("{path}.density: must be greater than 0"),
+            Some(density) => Some((density, material(true)?.expect("the material is required"))),
+            None => None,
+        };
+
+        let transform = parse_transform(&section, "transform")?;
+        let motion = parse_transform(&section, "motion")?;
+        let place = |mesh: Arc<dyn Mesh>| -> Arc<dyn Mesh> {
+            let mesh: Arc<dyn Mesh> = match (transform, motion) {
+                (start, Some(end)) => Arc::new(Instance::moving(
+                    mesh,
+                    start.unwrap_or(Affine3A::IDENTITY),
+                    end,
+                )),
+                (Some(transform), None) => Arc::new(Instance::new(mesh, transform)),
+                (None, None) => mesh,
+            };
+
+            match &medium {
+                Some((density, phase)) => {
+                    Arc::new(ConstantMedium::new(mesh, *density, phase.clone()))
+                }
+                None => mesh,
+            }
+        };
+
+        let kind: String = section.required("type")?;
+        // Rays must be able to leave the volume, flat and infinite objects are only entered
+        if medium.is_some() && matches!(kind.as_str(), "plane" | "triangle" | "quad" | "disk") {
+            bail!("{path}.density: a {kind} does not enclose a volume");
+        }
+        match kind.as_str() {
+            "sphere" => {
+                allow(&["center", "radius"])?;
+                scene.add(place(Arc::new(Sphere::new(
+                    section.required("center")?,
+                    positive("radius")?,
+                    material(true)?.expect("the material is required"),
+                ))));
+            }
+            "triangle" => {
+                allow(&["vertices", "normals"])?;
+                let [a, b, c]: [Vec3; 3] = section.required("vertices")?;
+                let mut triangle =
+                    Triangle::new(a, b, c, material(true)?.expect("the material is required"));
+                if let Some(normals) = section.optional("normals")? {
+                    triangle = triangle.with_normals(normals);
+                }
+                scene.add(place(Arc::new(triangle)));
+            }
+            "quad" => {
+                allow(&["corner", "u", "v"])?;
+                let u: Vec3 = section.required("u")?;
+                let v: Vec3 = section.required("v")?;
+                if u.cross(v).length_squared() == 0. {
+                    bail!("{path}: u and v must not be parallel");
+                }
+                scene.add(place(Arc::new(Quad::new(
+                    section.required("corner")?,
+                    u,
+                    v,
+                    material(true)?.expect("the material is required"),
+                ))));
+            }
+            "plane" => {
+                allow(&["point", "normal"])?;
+                scene.add(place(Arc::new(Plane::new(
+                    section.required("point")?,
+                    direction("normal")?,
+                    material(true)?.expect("the material is required"),
+                ))));
+            }
+            "disk" => {
+                allow(&["center", "normal", "radius"])?;
+                scene.add(place(Arc::new(Disk::new(
+                    section.required("center")?,
+                    direction("normal")?,
+                    positive("radius")?,
+                    material(true)?.expect("the material is required"),
+                ))));
+            }
+            "box" => {
+                allow(&["min", "max"])?;
+                let (min, max): (Vec3, Vec3) = (section.required("min")?, section.required("max")?);
+                if min.cmpeq(max).any() {
+                    bail!("{path}: min and max must differ on every axis");
+                }
+                scene.add(place(Arc::new(Cuboid::new(
+                    min,
+                    max,
+                    material(true)?.expect("the material is required"),
+                ))));
+            }
+            "cylinder" => {
+                allow(&["base", "top", "radius"])?;
+                let (base, top) = segment("base", "top")?;
+                scene.add(place(Arc::new(Cylinder::new(
+                    base,
+                    top,
+                    positive("radius")?,
+                    material(true)?.expect("the material is required"),
+                ))));
+            }
+            "cone" => {
+                allow(&["base", "apex", "radius"])?;
+                let (base, apex) = segment("base", "apex")?;
+                scene.add(place(Arc::new(Cone::new(
+                    base,
+                    apex,
+                    positive("radius")?,
+                    material(true)?.expect("the material is required"),
+                ))));
+            }
+            "torus" => {
+                allow(&["center", "axis", "major_radius", "minor_radius"])?;
+                let axis = match section.optional::<Vec3>("axis")? {
+                    Some(_) => direction("axis")?,
+                    None => Vec3::Y,
+                };
+                scene.add(place(Arc::new(Torus::new(
+                    section.required("center")?,
+                    axis,
+                    positive("major_radius")?,
+                    positive("minor_radius")?,
+                    material(true)?.expect("the material is required"),
+                ))));
+            }
+            "mesh" => {
+                allow(&["path"])?;
+                let file: String = section.required("path")?;
+                let key = (file, section.optional::<String>("material")?);
+
+                // Instances of the same file share the triangles
+                let meshes = match loaded.get(&key) {
+                    Some(meshes) => meshes.clone(),
+                    None => {
+                        // Faces without a `usemtl` fall back to this material
+                        let default =
+                            material(false)?.unwrap_or_else(|| Lambertian::new(Vec3::splat(204.)));
+                        let meshes = obj::load(directory.join(&key.0), default)
+                            .with_context(|| format!("{path}.path"))?;
+                        loaded.entry(key).or_insert(meshes).clone()
+                    }
+                };
+                // The groups of a closed model are open on their own, the volume is
+                // bounded by all of them
+                if medium.is_some() {
+                    scene.add(place(Arc::new(Bvh::new(meshes))));
+                } else {
+                    scene.extend(meshes.into_iter().map(place));
+                }
+            }
+            other => bail!("{path}.type: unknown object type `{other}`, expected {OBJECT_TYPES}"),
+        }
+    }
+
+    Ok(scene)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use glam::Vec3;
+
+    use super::{default_scene, parse, DEFAULT_MAX_DEPTH};
+    use crate::utils::{background::Background, meshes::Mesh, ray::Ray};
+
+    const SCENE: &str = r#"
+[camera]
+look_from = [0, 0, 1]
+vfov = 60
+
+[render]
+samples = 2
+denoise = true
+
+[materials.green]
+type = "lambertian"
+color = [42, 157, 143]
+
+[[objects]]
+type = "sphere"
+center = [0, 0, -1]
+radius = 0.5
+material = "green"
+
+[[objects]]
+type = "triangle"
+vertices = [[-1, -1, -2], [1, -1, -2], [0, 1, -2]]
+material = "green"
+"#;
+
+    #[test]
+    fn scene_is_loaded() {
+        let scene = parse(SCENE, Path::new(".")).unwrap();
+
+        assert_eq!(scene.camera.look_from, Vec3::new(0., 0., 1.));
+        assert_eq!(scene.camera.look_at, Vec3::new(0., 0., -1.));
+        assert_eq!(scene.camera.vertical_fov, 60.);
+        assert_eq!(scene.samples, 2);
+        assert_eq!(scene.max_depth, DEFAULT_MAX_DEPTH);
+        assert!(scene.denoise);
+        assert_eq!(scene.scene.len(), 2);
+
+        let ray = Ray::new(Vec3::new(0., 0., 1.), Vec3::new(0., 0., -1.));
+        let hit = scene.scene.objects()[0]
+            .hit(&ray, 0.001, f32::INFINITY)
+            .unwrap();
+        assert_eq!(hit.distance, 1.5);
+    }
+
+    #[test]
+    fn default_scene_is_valid() {
+        assert_eq!(default_scene().scene.len(), 4);
+        assert_eq!(*default_scene().scene.background(), Background::default());
+    }
+
+    #[test]
+    fn lights_and_background() {
+        let scene = parse(
+            r#"
+[background]
+type = "solid"
+
+[materials.lamp]
+type = "diffuse_light"
+color = [255, 128, 0]
+intensity = 2
+
+[[objects]]
+type = "quad"
+corner = [-1, -1, -2]
+u = [2, 0, 0]
+v = [0, 2, 0]
+material = "lamp"
+"#,
+            Path::new("."),
+        )
+        .unwrap()
+        .scene;
+
+        assert_eq!(*scene.background(), Background::BLACK);
+
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(0., 0., -1.));
+        let hit = scene.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!(hit.material.scatter(&ray, &hit).is_none());
+        assert_eq!(hit.material.emitted(&ray, &hit), Vec3::new(510., 256., 0.));
+        assert_eq!(scene.material_id(&hit.material), Some(1));
+    }
+
+    #[test]
+    fn textured_materials() {
+        let scene = parse(
+            r#"
+[textures.tiles]
+type = "checker"
+scale = 1
+even = [255, 0, 0]
+odd = [0, 0, 255]
+
+[materials.floor]
+type = "lambertian"
+texture = "tiles"
+
+[[objects]]
+type = "quad"
+corner = [-2, -1, -2]
+u = [4, 0, 0]
+v = [0, 0, 4]
+material = "floor"
+"#,
+            Path::new("."),
+        )
+        .unwrap()
+        .scene;
+
+        let color = |x: f32| {
+            let ray = Ray::new(Vec3::new(x, 1., -0.5), Vec3::NEG_Y);
+            let hit = scene.hit(&ray, 0.001, f32::INFINITY).unwrap();
+            hit.material.scatter(&ray, &hit).unwrap().attenuation
+        };
+
+        // Cells (0, -1, -1) and (1, -1, -1)
+        assert_eq!(color(0.5), Vec3::new(1., 0., 0.));
+        assert_eq!(color(1.5), Vec3::new(0., 0., 1.));
+    }
+
+    #[test]
+    fn primitives() {
+        let scene = parse(
+            r#"
+[materials.white]
+type = "lambertian"
+color = [200, 200, 200]
+
+[[objects]]
+type = "plane"
+point = [0, -1, 0]
+normal = [0, 1, 0]
+material = "white"
+
+[[objects]]
+type = "disk"
+center = [0, 0, -5]
+normal = [0, 0, 1]
+radius = 0.5
+material = "white"
+
+[[objects]]
+type = "box"
+min = [2, 0, -1]
+max = [3, 1, -2]
+material = "white"
+
+[[objects]]
+type = "cylinder"
+base = [-3, 0, 0]
+top = [-3, 1, 0]
+radius = 0.5
+material = "white"
+
+[[objects]]
+type = "cone"
+base = [0, 3, 0]
+apex = [0, 4, 0]
+radius = 0.5
+material = "white"
+
+[[objects]]
+type = "torus"
+center = [0, 0.5, 5]
+major_radius = 1
+minor_radius = 0.25
+material = "white"
+"#,
+            Path::new("."),
+        )
+        .unwrap()
+        .scene;
+
+        assert_eq!(scene.len(), 6);
+
+        let distance = |direction: Vec3| {
+            let ray = Ray::new(Vec3::new(0., 0.5, 0.), direction);
+            scene.hit(&ray, 0.001, f32::INFINITY).unwrap().distance
+        };
+
+        assert!((distance(Vec3::NEG_Y) - 1.5).abs() < 1e-5);
+        assert!((distance(Vec3::NEG_Z) - 5.).abs() < 1e-5);
+        assert!((distance(Vec3::new(1., 0., -0.6)) - 2.).abs() < 1e-5);
+        assert!((distance(Vec3::NEG_X) - 2.5).abs() < 1e-5);
+        assert!((distance(Vec3::Y) - 2.5).abs() < 1e-5);
+        assert!((distance(Vec3::Z) - 3.75).abs() < 1e-4);
+    }
+
+    #[test]
+    fn transformed_objects() {
+        let scene = parse(
+            r#"
+[materials.white]
+type = "lambertian"
+color = [200, 200, 200]
+
+[[objects]]
+type = "box"
+min = [-0.5, -0.5, -0.5]
+max = [0.5, 0.5, 0.5]
+material = "white"
+transform = { scale = [1, 1, 4], rotate = [0, 90, 0], translate = [0, 0, -3] }
+"#,
+            Path::new("."),
+        )
+        .unwrap()
+        .scene;
+
+        // Stretched along z, then turned along x
+        let aabb = scene.objects()[0].bounding_box();
+        assert!(aabb.min.abs_diff_eq(Vec3::new(-2., -0.5, -3.5), 1e-5));
+        assert!(aabb.max.abs_diff_eq(Vec3::new(2., 0.5, -2.5), 1e-5));
+
+        let ray = Ray::new(Vec3::new(1.5, 0., 0.), Vec3::NEG_Z);
+        let hit = scene.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.distance - 2.5).abs() < 1e-5);
+        assert!(hit.normal.abs_diff_eq(Vec3::Z, 1e-5));
+    }
+
+    #[test]
+    fn moving_objects() {
+        let scene = parse(
+            r#"
+[camera]
+shutter = [0, 1]
+
+[materials.white]
+type = "lambertian"
+color = [200, 200, 200]
+
+[[objects]]
+type = "sphere"
+center = [0, 0, -3]
+radius = 0.5
+material = "white"
+motion = { translate = [2, 0, 0] }
+"#,
+            Path::new("."),
+        )
+        .unwrap();
+
+        assert_eq!(scene.camera.shutter, (0., 1.));
+
+        let hit = |x: f32, time: f32| {
+            let ray = Ray::new(Vec3::new(x, 0., 0.), Vec3::NEG_Z).with_time(time);
+            scene.scene.hit(&ray, 0.001, f32::INFINITY).is_some()
+        };
+        assert!(hit(0., 0.) && !hit(2., 0.));
+        assert!(!hit(0., 1.) && hit(2., 1.));
+    }
+
+    #[test]
+    fn volumes() {
+        let scene = parse(
+            r#"
+[materials.smoke]
+type = "isotropic"
+color = [255, 255, 255]
+
+[[objects]]
+type = "box"
+min = [-1, -1, -3]
+max = [1, 1, -1]
+material = "smoke"
+density = 1000
+"#,
+            Path::new("."),
+        )
+        .unwrap()
+        .scene;
+
+        // Anything but the flat face of the box, the fog is hit right after entering it
+        let ray = Ray::new(Vec3::ZERO, Vec3::NEG_Z);
+        let hit = scene.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!(hit.distance > 1. && hit.distance < 1.1);
+        assert!(hit.material.scatter(&ray, &hit).unwrap().pdf.is_some());
+
+        // The same box as a model, the front and back faces are in different groups
+        let directory = std::env::temp_dir();
+        std::fs::write(
+            directory.join("graphics-3d-box.obj"),
+            "v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\n\
+             v -1 -1 -3\nv 1 -1 -3\nv 1 1 -3\nv -1 1 -3\n\
+             g front\nf 1 2 3 4\nf 1 4 8 5\nf 4 3 7 8\n\
+             g back\nf 5 8 7 6\nf 2 6 7 3\nf 1 5 6 2\n",
+        )
+        .unwrap();
+        let scene = parse(
+            r#"
+[materials.smoke]
+type = "isotropic"
+color = [255, 255, 255]
+
+[[objects]]
+type = "mesh"
+path = "graphics-3d-box.obj"
+material = "smoke"
+density = 1000
+"#,
+            &directory,
+        )
+        .unwrap()
+        .scene;
+
+        assert_eq!(scene.len(), 1);
+        let hit = scene.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!(hit.distance > 1. && hit.distance < 1.1);
+    }
+
+    #[test]
+    fn cornell_box_is_valid() {
+        let scene = parse(include_str!("../../scenes/cornell.toml"), Path::new(".")).unwrap();
+        assert_eq!(scene.scene.len(), 8);
+    }
+
+    #[test]
+    fn invalid_scenes_are_explained() {
+        let error = |source: &str| format!("{:#}", parse(source, Path::new(".")).err().unwrap());
+
+        assert_eq!(
+            error("[[objects]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"red\""),
+            "objects[0].material: unknown material `red`"
+        );
+        assert_eq!(
+            error("[[objects]]\ntype = \"cube\""),
+            "objects[0].type: unknown object type `cube`, expected sphere, triangle, quad, plane, disk, box, cylinder, cone, torus or mesh"
+        );
+        assert_eq!(
+            error("[camera]\nlook_at = [0, 0, 0]"),
+            "camera: look_from and look_at must be different points"
+        );
+        assert_eq!(
+            error("[background]\ntype = \"sky\""),
+            "background.type: unknown background `sky`, expected solid, gradient or environment"
+        );
+        assert_eq!(
+            error("[background]\ntype = \"environment\"\npath = \"sky.hdr\""),
+            "background.path: cannot read ./sky.hdr: No such file or directory (os error 2)"
+        );
+
+        let directory = std::env::temp_dir();
+        std::fs::write(directory.join("graphics-3d-empty.ppm"), "P6 0 0 255\n").unwrap();
+        let empty = parse(
+            "[background]\ntype = \"environment\"\npath = \"graphics-3d-empty.ppm\"",
+            &directory,
+        );
+        assert_eq!(
+            format!("{:#}", empty.err().unwrap()),
+            "background.path: the image is empty"
+        );
+        assert_eq!(
+            error("[materials.a]\ntype = \"lambertian\"\ntexture = \"wood\""),
+            "materials.a.texture: unknown texture `wood`"
+        );
+        assert_eq!(
+            error("[materials.a]\ntype = \"lambertian\""),
+            "materials.a: missing `color` or `texture`"
+        );
+        assert_eq!(
+            error("[render]\nsample = 3"),
+            "render: unknown key `sample`, expected one of: samples, max_depth, tone_mapping, exposure, denoise"
+        );
+        assert_eq!(
+            error("[render]\ntone_mapping = \"filmic\""),
+            "render.tone_mapping: unknown tone mapper `filmic`, expected clamp, reinhard or aces"
+        );
+        assert_eq!(
+            error("[materials.a]\ntype = \"principled\"\ncolor = [1, 2, 3]\nroughness = 2"),
+            "materials.a.roughness: must be between 0 and 1"
+        );
+        assert_eq!(
+            error("[[objects]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nsize = 1"),
+            "objects[0]: unknown key `size`, expected one of: type, material, transform, motion, density, center, radius"
+        );
+        assert_eq!(
+            error("[materials.a]\ntype = \"dielectric\"\nior = 1.5\n[[objects]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"a\"\ntransform = { scale = 0 }"),
+            "objects[0].transform.scale: must not be zero"
+        );
+        assert_eq!(
+            error("[materials.a]\ntype = \"isotropic\"\ncolor = [1, 2, 3]\n[[objects]]\ntype = \"plane\"\npoint = [0, 0, 0]\nnormal = [0, 1, 0]\nmaterial = \"a\"\ndensity = 1"),
+            "objects[0].density: a plane does not enclose a volume"
+        );
+        assert_eq!(
+            error("[materials.a]\ntype = \"metal\"\ncolor = [1, 2, 3]\n[[objects]]\ntype = \"box\"\nmin = [0, 0, 0]\nmax = [1, 0, 1]\nmaterial = \"a\""),
+            "objects[0]: min and max must differ on every axis"
+        );
+        assert_eq!(
+            error("[materials.a]\ntype = \"metal\"\ncolor = [1, 2]"),
+            "materials.a.color: expected an array of 3 numbers, found an array"
+        );
+        assert_eq!(
+            error("[[objects]]\ntype = \"mesh\"\npath = \"missing.obj\""),
+            "objects[0].path: cannot read ./missing.obj: No such file or directory (os error 2)"
+        );
+    }
+}
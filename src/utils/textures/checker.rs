@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use glam::Vec3;
+
+use crate::utils::colors::ColorVec;
+
+use super::{solid::SolidColor, Texture};
+
+/// Alternates two textures in cubes of side `scale` filling the space,
+/// so it does not depend on the texture coordinates of the mesh
+#[derive(Debug)]
+pub struct Checker {
+    inverse_scale: f32,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl Checker {
+    pub fn new(scale: f32, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Arc<Self> {
+        Arc::new(Self {
+            inverse_scale: 1. / scale,
+            even,
+            odd,
+        })
+    }
+
+    pub fn colors(scale: f32, even: ColorVec, odd: ColorVec) -> Arc<Self> {
+        Self::new(scale, SolidColor::new(even), SolidColor::new(odd))
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f32, v: f32, point: Vec3) -> ColorVec {
+        let cell = (point * self.inverse_scale).floor();
+
+        if (cell.x + cell.y + cell.z).rem_euclid(2.) == 0. {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::Checker;
+    use crate::utils::{
+        colors::{BLUE, RED},
+        textures::Texture,
+    };
+
+    #[test]
+    fn alternates_in_every_direction() {
+        let checker = Checker::colors(0.5, RED, BLUE);
+        let at = |x: f32, y: f32, z: f32| checker.value(0., 0., Vec3::new(x, y, z));
+
+        assert_eq!(at(0.1, 0.1, 0.1), RED);
+        assert_eq!(at(0.6, 0.1, 0.1), BLUE);
+        assert_eq!(at(0.6, 0.6, 0.1), RED);
+        assert_eq!(at(-0.1, 0.1, 0.1), BLUE);
+        assert_eq!(at(0.1, 0.1, -0.6), RED);
+    }
+}
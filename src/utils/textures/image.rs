@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use glam::Vec3;
+
+use crate::utils::{colors::ColorVec, image::Image};
+
+use super::Texture;
+
+/// Image wrapped around the texture coordinates, `v` goes from the bottom to the top.
+/// Coordinates outside of 0 to 1 repeat the image
+#[derive(Debug)]
+pub struct ImageTexture {
+    image: Image,
+}
+
+impl ImageTexture {
+    pub fn new(image: Image) -> Arc<Self> {
+        Arc::new(Self { image })
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _point: Vec3) -> ColorVec {
+        let (width, height) = (self.image.width, self.image.height);
+        if width == 0 || height == 0 {
+            return Vec3::ZERO;
+        }
+
+        // Bilinear filtering between the centers of the 4 closest pixels
+        let x = u.rem_euclid(1.) * width as f32 - 0.5;
+        let y = (1. - v.rem_euclid(1.)) * height as f32 - 0.5;
+
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+
+        let pixel = |x: f32, y: f32| {
+            let x = (x as isize).rem_euclid(width as isize) as usize;
+            let y = (y as isize).rem_euclid(height as isize) as usize;
+            self.image.pixel(x, y)
+        };
+
+        let top = pixel(x0, y0).lerp(pixel(x0 + 1., y0), tx);
+        let bottom = pixel(x0, y0 + 1.).lerp(pixel(x0 + 1., y0 + 1.), tx);
+
+        top.lerp(bottom, ty) * 255.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::ImageTexture;
+    use crate::utils::{image::Image, textures::Texture};
+
+    #[test]
+    fn bilinear_and_repeated() {
+        // Black on the left, white on the right
+        let texture = ImageTexture::new(Image {
+            width: 2,
+            height: 1,
+            pixels: vec![Vec3::ZERO, Vec3::ONE],
+        });
+        let at = |u: f32, v: f32| texture.value(u, v, Vec3::ZERO).x;
+
+        assert_eq!(at(0.25, 0.5), 0.);
+        assert_eq!(at(0.75, 0.5), 255.);
+        assert_eq!(at(0.5, 0.9), 127.5);
+        // Wraps around the left edge
+        assert_eq!(at(0., 0.5), 127.5);
+        assert_eq!(at(1.75, 0.5), 255.);
+    }
+}
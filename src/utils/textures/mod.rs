@@ -0,0 +1,16 @@
+use std::fmt::Debug;
+
+use glam::Vec3;
+
+use super::colors::ColorVec;
+
+pub mod checker;
+pub mod image;
+pub mod noise;
+pub mod solid;
+
+/// Color varying across a surface
+pub trait Texture: Debug + Send + Sync {
+    /// Color at the texture coordinates `(u, v)` of the hit `point`, in the 0 to 255 scale
+    fn value(&self, u: f32, v: f32, point: Vec3) -> ColorVec;
+}
@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use glam::Vec3;
+use rand::seq::SliceRandom;
+
+use crate::utils::{colors::ColorVec, random_unit_vector};
+
+use super::Texture;
+
+const POINTS: usize = 256;
+
+/// Perlin gradient noise, smooth pseudo random values from -1 to 1 over the space
+#[derive(Debug)]
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    permutations: [Vec<usize>; 3],
+}
+
+impl Default for Perlin {
+    fn default() -> Self {
+        let permutation = || {
+            let mut p: Vec<usize> = (0..POINTS).collect();
+            p.shuffle(&mut rand::rng());
+            p
+        };
+
+        Perlin {
+            gradients: (0..POINTS).map(|_| random_unit_vector()).collect(),
+            permutations: [permutation(), permutation(), permutation()],
+        }
+    }
+}
+
+impl Perlin {
+    pub fn noise(&self, point: Vec3) -> f32 {
+        let cell = point.floor();
+        let t = point - cell;
+        // Hermite smoothing, hides the grid
+        let smooth = t * t * (3. - 2. * t);
+
+        let mut sum = 0.;
+        for i in 0..2 {
+            for j in 0..2 {
+                for k in 0..2 {
+                    let corner = Vec3::new(i as f32, j as f32, k as f32);
+                    let index = self.permutations[0][(cell.x as i64 + i) as usize & (POINTS - 1)]
+                        ^ self.permutations[1][(cell.y as i64 + j) as usize & (POINTS - 1)]
+                        ^ self.permutations[2][(cell.z as i64 + k) as usize & (POINTS - 1)];
+
+                    let weight = corner * smooth + (1. - corner) * (1. - smooth);
+                    sum += weight.element_product() * self.gradients[index].dot(t - corner);
+                }
+            }
+        }
+
+        sum
+    }
+
+    /// Sum of `depth` octaves of noise, each at double the frequency and half the weight
+    pub fn turbulence(&self, point: Vec3, depth: usize) -> f32 {
+        let mut sum = 0.;
+        let mut point = point;
+        let mut weight = 1.;
+
+        for _ in 0..depth {
+            sum += weight * self.noise(point);
+            weight /= 2.;
+            point *= 2.;
+        }
+
+        sum.abs()
+    }
+}
+
+/// Marble like veins of `color`, `scale` is how many veins fit in a unit
+#[derive(Debug)]
+pub struct Noise {
+    perlin: Perlin,
+    scale: f32,
+    color: ColorVec,
+}
+
+impl Noise {
+    pub fn new(scale: f32, color: ColorVec) -> Arc<Self> {
+        Arc::new(Self {
+            perlin: Perlin::default(),
+            scale,
+            color,
+        })
+    }
+}
+
+impl Texture for Noise {
+    fn value(&self, _u: f32, _v: f32, point: Vec3) -> ColorVec {
+        let phase = self.scale * point.z + 10. * self.perlin.turbulence(point, 7);
+        self.color * 0.5 * (1. + phase.sin())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::{Noise, Perlin};
+    use crate::utils::{colors::WHITE, textures::Texture};
+
+    #[test]
+    fn noise_is_smooth_and_bounded() {
+        let perlin = Perlin::default();
+
+        // Zero on the lattice, continuous in between
+        assert_eq!(perlin.noise(Vec3::new(3., -2., 7.)), 0.);
+
+        let p = Vec3::new(0.3, 1.7, -2.2);
+        let close = perlin.noise(p + Vec3::splat(1e-3));
+        assert!((perlin.noise(p) - close).abs() < 1e-2);
+
+        for i in 0..1000 {
+            let n = perlin.noise(Vec3::new(
+                i as f32 * 0.37,
+                i as f32 * 0.11,
+                -(i as f32) * 0.07,
+            ));
+            assert!((-1.5..=1.5).contains(&n));
+        }
+    }
+
+    #[test]
+    fn marble_stays_within_the_color() {
+        let marble = Noise::new(4., WHITE);
+        for i in 0..100 {
+            let c = marble.value(0., 0., Vec3::splat(i as f32 * 0.13));
+            assert!(c.cmpge(Vec3::ZERO).all() && c.cmple(WHITE).all());
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use std::sync::Arc;
+
+use glam::Vec3;
+
+use crate::utils::colors::ColorVec;
+
+use super::Texture;
+
+#[derive(Debug)]
+pub struct SolidColor(ColorVec);
+
+impl SolidColor {
+    pub fn new(color: ColorVec) -> Arc<Self> {
+        Arc::new(Self(color))
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f32, _v: f32, _point: Vec3) -> ColorVec {
+        self.0
+    }
+}
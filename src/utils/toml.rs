@@ -0,0 +1,660 @@
+//! Parser for the subset of TOML used by scene files.
+//!
+//! Supports comments, `[tables]`, `[[arrays.of.tables]]`, dotted keys, basic
+//! and literal strings, integers, floats, booleans, (multiline) arrays and
+//! inline tables. Dates and multiline strings are not supported.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, ensure};
+use glam::Vec3;
+
+pub type Table = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Float(_) => "a float",
+            Value::Boolean(_) => "a boolean",
+            Value::Array(_) => "an array",
+            Value::Table(_) => "a table",
+        }
+    }
+}
+
+pub fn parse(source: &str) -> anyhow::Result<Table> {
+    Parser {
+        chars: source.chars().collect(),
+        position: 0,
+        line: 1,
+    }
+    .parse()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> anyhow::Error {
+        anyhow!("line {}: {message}", self.line)
+    }
+
+    fn expect(&mut self, expected: char) -> anyhow::Result<()> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some('\n') => {
+                self.line -= 1;
+                Err(self.error(format!("expected `{expected}`, found the end of the line")))
+            }
+            Some(c) => Err(self.error(format!("expected `{expected}`, found `{c}`"))),
+            None => Err(self.error(format!("expected `{expected}`, found the end of the file"))),
+        }
+    }
+
+    /// Skips spaces and tabs
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.next();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), Some('\n') | None) {
+                self.next();
+            }
+        }
+    }
+
+    /// Skips whitespace, newlines and comments
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n' | '\r') => {
+                    self.next();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> anyhow::Result<()> {
+        self.skip_spaces();
+        self.skip_comment();
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.next();
+                Ok(())
+            }
+            Some('\r') => {
+                self.next();
+                self.expect('\n')
+            }
+            Some(c) => Err(self.error(format!("expected the end of the line, found `{c}`"))),
+        }
+    }
+
+    fn parse(mut self) -> anyhow::Result<Table> {
+        let mut root = Table::new();
+        let mut current: Vec<String> = vec![];
+        let mut defined: Vec<Vec<String>> = vec![];
+
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                None => return Ok(root),
+                Some('[') => {
+                    self.next();
+                    let array = self.peek() == Some('[');
+                    if array {
+                        self.next();
+                    }
+
+                    self.skip_spaces();
+                    let path = self.key_path()?;
+                    self.expect(']')?;
+                    if array {
+                        self.expect(']')?;
+                    }
+                    self.end_of_line()?;
+
+                    if array {
+                        let (last, parent) = path.split_last().expect("paths are never empty");
+                        let parent = self.navigate(&mut root, parent)?;
+                        match parent
+                            .entry(last.clone())
+                            .or_insert_with(|| Value::Array(vec![]))
+                        {
+                            Value::Array(tables)
+                                if tables.iter().all(|t| matches!(t, Value::Table(_))) =>
+                            {
+                                tables.push(Value::Table(Table::new()))
+                            }
+                            _ => {
+                                return Err(self.error(format!(
+                                    "`{}` is not an array of tables",
+                                    path.join(".")
+                                )))
+                            }
+                        }
+                    } else {
+                        if defined.contains(&path) {
+                            return Err(
+                                self.error(format!("table `{}` is defined twice", path.join(".")))
+                            );
+                        }
+                        self.navigate(&mut root, &path)?;
+                        defined.push(path.clone());
+                    }
+
+                    current = path;
+                }
+                Some(_) => {
+                    let path = self.key_path()?;
+                    self.expect('=')?;
+                    self.skip_spaces();
+                    let value = self.value()?;
+                    self.end_of_line()?;
+
+                    let table = self.navigate(&mut root, &current)?;
+                    self.insert(table, &path, value)?;
+                }
+            }
+        }
+    }
+
+    /// Walks down to the table at `path`, creating missing tables and
+    /// entering the last element of arrays of tables
+    fn navigate<'t>(
+        &self,
+        mut table: &'t mut Table,
+        path: &[String],
+    ) -> anyhow::Result<&'t mut Table> {
+        for key in path {
+            table = match table
+                .entry(key.clone())
+                .or_insert_with(|| Value::Table(Table::new()))
+            {
+                Value::Table(t) => t,
+                Value::Array(a) => match a.last_mut() {
+                    Some(Value::Table(t)) => t,
+                    _ => return Err(self.error(format!("`{key}` is not a table"))),
+                },
+                _ => return Err(self.error(format!("`{key}` is not a table"))),
+            };
+        }
+
+        Ok(table)
+    }
+
+    fn insert(&self, table: &mut Table, path: &[String], value: Value) -> anyhow::Result<()> {
+        let (last, parent) = path.split_last().expect("paths are never empty");
+        let table = self.navigate(table, parent)?;
+
+        if table.contains_key(last) {
+            return Err(self.error(format!("key `{}` is defined twice", path.join("."))));
+        }
+        table.insert(last.clone(), value);
+
+        Ok(())
+    }
+
+    fn key_path(&mut self) -> anyhow::Result<Vec<String>> {
+        let mut path = vec![self.key()?];
+        self.skip_spaces();
+
+        while self.peek() == Some('.') {
+            self.next();
+            self.skip_spaces();
+            path.push(self.key()?);
+            self.skip_spaces();
+        }
+
+        Ok(path)
+    }
+
+    fn key(&mut self) -> anyhow::Result<String> {
+        match self.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let mut key = String::new();
+                while let Some(c) = self
+                    .peek()
+                    .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+                {
+                    key.push(c);
+                    self.next();
+                }
+
+                match (key.is_empty(), self.peek()) {
+                    (false, _) => Ok(key),
+                    (true, Some(c)) if c != '\n' => {
+                        Err(self.error(format!("expected a key, found `{c}`")))
+                    }
+                    _ => Err(self.error("expected a key")),
+                }
+            }
+        }
+    }
+
+    fn value(&mut self) -> anyhow::Result<Value> {
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some('t' | 'f') => {
+                let word = self.word();
+                match word.as_str() {
+                    "true" => Ok(Value::Boolean(true)),
+                    "false" => Ok(Value::Boolean(false)),
+                    _ => Err(self.error(format!("invalid value `{word}`"))),
+                }
+            }
+            Some(c) if c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'i' | 'n') => {
+                self.number()
+            }
+            Some('\n') | None => Err(self.error("expected a value")),
+            Some(c) => Err(self.error(format!("invalid value starting with `{c}`"))),
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(c) = self
+            .peek()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '.'))
+        {
+            word.push(c);
+            self.next();
+        }
+        word
+    }
+
+    fn number(&mut self) -> anyhow::Result<Value> {
+        let word = self.word();
+        let cleaned = word.replace('_', "");
+
+        if let Ok(integer) = cleaned.parse::<i64>() {
+            return Ok(Value::Integer(integer));
+        }
+
+        let float = match cleaned.trim_start_matches(['+', '-']) {
+            "inf" | "nan" => cleaned.parse::<f64>().ok(),
+            _ if cleaned
+                .chars()
+                .any(|c| c.is_ascii_alphabetic() && c != 'e' && c != 'E') =>
+            {
+                None
+            }
+            _ => cleaned.parse::<f64>().ok(),
+        };
+
+        float
+            .map(Value::Float)
+            .ok_or_else(|| self.error(format!("invalid number `{word}`")))
+    }
+
+    fn basic_string(&mut self) -> anyhow::Result<String> {
+        self.expect('"')?;
+        let mut string = String::new();
+
+        loop {
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => {
+                    let escaped = match self.next() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some(c) => {
+                            return Err(self.error(format!("unknown escape sequence `\\{c}`")))
+                        }
+                        None => return Err(self.error("unterminated string")),
+                    };
+                    string.push(escaped);
+                }
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(c) => string.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> anyhow::Result<String> {
+        self.expect('\'')?;
+        let mut string = String::new();
+
+        loop {
+            match self.next() {
+                Some('\'') => return Ok(string),
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(c) => string.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> anyhow::Result<Value> {
+        self.expect('[')?;
+        let mut values = vec![];
+
+        loop {
+            self.skip_blank();
+            if self.peek() == Some(']') {
+                self.next();
+                return Ok(Value::Array(values));
+            }
+
+            values.push(self.value()?);
+
+            self.skip_blank();
+            match self.peek() {
+                Some(',') => {
+                    self.next();
+                }
+                Some(']') => {}
+                Some(c) => return Err(self.error(format!("expected `,` or `]`, found `{c}`"))),
+                None => return Err(self.error("unterminated array")),
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> anyhow::Result<Value> {
+        self.expect('{')?;
+        let mut table = Table::new();
+
+        self.skip_spaces();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(Value::Table(table));
+        }
+
+        loop {
+            self.skip_spaces();
+            let path = self.key_path()?;
+            self.expect('=')?;
+            self.skip_spaces();
+            let value = self.value()?;
+            self.insert(&mut table, &path, value)?;
+
+            self.skip_spaces();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(Value::Table(table)),
+                Some(c) => return Err(self.error(format!("expected `,` or `}}`, found `{c}`"))),
+                None => return Err(self.error("unterminated inline table")),
+            }
+        }
+    }
+}
+
+/// Conversion from a parsed value, used by [`Section`]
+pub trait FromValue: Sized {
+    const EXPECTED: &'static str;
+
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl FromValue for f32 {
+    const EXPECTED: &'static str = "a number";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Integer(i) => Some(*i as f32),
+            Value::Float(f) => Some(*f as f32),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for usize {
+    const EXPECTED: &'static str = "a positive integer";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Integer(i) => (*i).try_into().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for bool {
+    const EXPECTED: &'static str = "a boolean";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for String {
+    const EXPECTED: &'static str = "a string";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for Vec3 {
+    const EXPECTED: &'static str = "an array of 3 numbers";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Array(a) if a.len() == 3 => Some(Vec3::new(
+                f32::from_value(&a[0])?,
+                f32::from_value(&a[1])?,
+                f32::from_value(&a[2])?,
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromValue, const N: usize> FromValue for [T; N] {
+    const EXPECTED: &'static str = "an array";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Array(a) if a.len() == N => a
+                .iter()
+                .map(T::from_value)
+                .collect::<Option<Vec<_>>>()?
+                .try_into()
+                .ok(),
+            _ => None,
+        }
+    }
+}
+
+/// A table together with its path in the document, to produce readable errors
+#[derive(Debug, Clone, Copy)]
+pub struct Section<'a> {
+    pub path: &'a str,
+    pub table: &'a Table,
+}
+
+impl<'a> Section<'a> {
+    pub fn new(path: &'a str, value: &'a Value) -> anyhow::Result<Section<'a>> {
+        match value {
+            Value::Table(table) => Ok(Section { path, table }),
+            other => bail!("{path}: expected a table, found {}", other.kind()),
+        }
+    }
+
+    /// Fails on keys outside of `allowed`, typos would otherwise be silently ignored
+    pub fn allow(&self, allowed: &[&str]) -> anyhow::Result<()> {
+        for key in self.table.keys() {
+            ensure!(
+                allowed.contains(&key.as_str()),
+                "{}: unknown key `{key}`, expected one of: {}",
+                self.path,
+                allowed.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn optional<T: FromValue>(&self, key: &str) -> anyhow::Result<Option<T>> {
+        let Some(value) = self.table.get(key) else {
+            return Ok(None);
+        };
+
+        T::from_value(value).map(Some).ok_or_else(|| {
+            anyhow!(
+                "{}.{key}: expected {}, found {}",
+                self.path,
+                T::EXPECTED,
+                value.kind()
+            )
+        })
+    }
+
+    pub fn required<T: FromValue>(&self, key: &str) -> anyhow::Result<T> {
+        self.optional(key)?
+            .ok_or_else(|| anyhow!("{}: missing `{key}`", self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Section, Table, Value};
+
+    #[test]
+    fn tables_and_values() {
+        let document = parse(
+            r#"
+# comment
+title = "scene" # trailing comment
+literal = 'C:\path'
+
+[render]
+samples = 1_000
+exposure = -1.5e-1
+enabled = true
+
+[materials.red]
+color = [231, 111.5, 81]
+
+[[objects]]
+inline = { radius = 2, center.x = 1 }
+
+[[objects]]
+list = [
+    [0, 0, 0], # first
+    [1, 1, 1],
+]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(document["title"], Value::String("scene".into()));
+        assert_eq!(document["literal"], Value::String("C:\\path".into()));
+
+        let Value::Table(render) = &document["render"] else {
+            panic!()
+        };
+        assert_eq!(render["samples"], Value::Integer(1000));
+        assert_eq!(render["exposure"], Value::Float(-0.15));
+        assert_eq!(render["enabled"], Value::Boolean(true));
+
+        let Value::Table(materials) = &document["materials"] else {
+            panic!()
+        };
+        assert!(matches!(&materials["red"], Value::Table(t) if t.contains_key("color")));
+
+        let Value::Array(objects) = &document["objects"] else {
+            panic!()
+        };
+        assert_eq!(objects.len(), 2);
+        let Value::Table(first) = &objects[0] else {
+            panic!()
+        };
+        let Value::Table(inline) = &first["inline"] else {
+            panic!()
+        };
+        assert_eq!(inline["radius"], Value::Integer(2));
+        assert!(matches!(&inline["center"], Value::Table(t) if t["x"] == Value::Integer(1)));
+    }
+
+    #[test]
+    fn syntax_errors_report_the_line() {
+        let error = |source: &str| parse(source).unwrap_err().to_string();
+
+        assert_eq!(error("a = 1\nb = \n"), "line 2: expected a value");
+        assert_eq!(error("a = 1\na = 2"), "line 2: key `a` is defined twice");
+        assert_eq!(error("[a]\n[a]"), "line 2: table `a` is defined twice");
+        assert_eq!(error("a = \"open"), "line 1: unterminated string");
+        assert_eq!(error("a = [1 2]"), "line 1: expected `,` or `]`, found `2`");
+        assert_eq!(
+            error("a = 1 b"),
+            "line 1: expected the end of the line, found `b`"
+        );
+        assert_eq!(error("a = 12x"), "line 1: invalid number `12x`");
+    }
+
+    #[test]
+    fn sections_validate_types() {
+        let document = parse("radius = \"big\"\ncenter = [1, 2, 3]").unwrap();
+        let section = Section {
+            path: "sphere",
+            table: &document,
+        };
+
+        assert_eq!(
+            section.optional::<glam::Vec3>("center").unwrap(),
+            Some(glam::Vec3::new(1., 2., 3.))
+        );
+        assert_eq!(
+            section.required::<f32>("radius").unwrap_err().to_string(),
+            "sphere.radius: expected a number, found a string"
+        );
+        assert_eq!(
+            section.required::<f32>("missing").unwrap_err().to_string(),
+            "sphere: missing `missing`"
+        );
+        assert_eq!(
+            section.allow(&["center"]).unwrap_err().to_string(),
+            "sphere: unknown key `radius`, expected one of: center"
+        );
+        assert!(Section::new("x", &Value::Table(Table::new())).is_ok());
+    }
+}
@@ -0,0 +1,140 @@
+//! From the linear light of the renderer to the colors of the screen
+
+use std::{fmt, str::FromStr};
+
+use anyhow::bail;
+use glam::Vec3;
+
+use super::colors::{self, Color};
+
+/// Squeezes linear light, unbounded, into the 0 to 1 range of a display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneMapper {
+    /// Everything brighter than white is clipped
+    #[default]
+    Clamp,
+    /// Compresses the highlights on the luminance, keeping the hue
+    Reinhard,
+    /// Filmic curve of the ACES standard, with a slight contrast boost
+    Aces,
+}
+
+impl ToneMapper {
+    pub const ALL: [ToneMapper; 3] = [ToneMapper::Clamp, ToneMapper::Reinhard, ToneMapper::Aces];
+
+    pub fn map(self, color: Vec3) -> Vec3 {
+        let color = color.max(Vec3::ZERO);
+
+        let mapped = match self {
+            ToneMapper::Clamp => color,
+            ToneMapper::Reinhard => {
+                let luminance = colors::luminance(color);
+                if luminance <= 0. {
+                    return Vec3::ZERO;
+                }
+                color / (1. + luminance)
+            }
+            // Krzysztof Narkowicz's fit of the reference transform
+            ToneMapper::Aces => {
+                color * (2.51 * color + 0.03) / (color * (2.43 * color + 0.59) + 0.14)
+            }
+        };
+
+        mapped.clamp(Vec3::ZERO, Vec3::ONE)
+    }
+
+    /// The one after this, to cycle through them
+    #[must_use]
+    pub fn next(self) -> ToneMapper {
+        let index = Self::ALL.iter().position(|t| *t == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+impl fmt::Display for ToneMapper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ToneMapper::Clamp => "clamp",
+            ToneMapper::Reinhard => "reinhard",
+            ToneMapper::Aces => "aces",
+        })
+    }
+}
+
+impl FromStr for ToneMapper {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> anyhow::Result<ToneMapper> {
+        match Self::ALL.into_iter().find(|t| t.to_string() == name) {
+            Some(tone_mapper) => Ok(tone_mapper),
+            None => bail!("unknown tone mapper `{name}`, expected clamp, reinhard or aces"),
+        }
+    }
+}
+
+/// How a linear framebuffer is shown
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ToneMapping {
+    pub tone_mapper: ToneMapper,
+    /// In stops, every one doubles the light
+    pub exposure: f32,
+}
+
+impl ToneMapping {
+    /// Display color of a linear `pixel`, where 1 is white
+    pub fn color(&self, pixel: Vec3) -> Color {
+        let mapped = self.tone_mapper.map(pixel * self.exposure.exp2());
+        let encoded = mapped.map(colors::linear_to_srgb);
+
+        colors::vec3àto_color_uncorrected(&(encoded * 255. + 0.5))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::{ToneMapper, ToneMapping};
+
+    #[test]
+    fn highlights_are_compressed() {
+        let bright = Vec3::new(2., 1., 1.);
+
+        assert_eq!(ToneMapper::Clamp.map(bright), Vec3::ONE);
+
+        // Brighter stays brighter, and the hue is kept
+        let reinhard = ToneMapper::Reinhard.map(bright);
+        assert!(reinhard.max_element() < 1.);
+        assert!(ToneMapper::Reinhard.map(bright * 2.).x > reinhard.x);
+        assert!((reinhard.x / reinhard.y - 2.).abs() < 1e-5);
+
+        let aces = ToneMapper::Aces.map(bright);
+        assert!(aces.cmple(Vec3::ONE).all() && aces.x > aces.y && aces.y == aces.z);
+
+        for tone_mapper in ToneMapper::ALL {
+            assert_eq!(tone_mapper.map(Vec3::ZERO), Vec3::ZERO);
+            assert_eq!(tone_mapper.map(Vec3::splat(-1.)), Vec3::ZERO);
+            assert_eq!(
+                tone_mapper.to_string().parse::<ToneMapper>().unwrap(),
+                tone_mapper
+            );
+        }
+        assert!("filmic".parse::<ToneMapper>().is_err());
+    }
+
+    #[test]
+    fn colors_are_srgb_encoded() {
+        let mapping = ToneMapping::default();
+
+        assert_eq!(mapping.color(Vec3::ZERO), 0);
+        assert_eq!(mapping.color(Vec3::ONE), 0xFFFFFF);
+        // Middle gray is encoded around 188
+        assert_eq!(mapping.color(Vec3::new(0.5, 0., 0.)), 188 << 16);
+
+        let darker = ToneMapping {
+            exposure: -1.,
+            ..mapping
+        };
+        assert_eq!(darker.color(Vec3::new(0., 0., 1.)), 188);
+    }
+}
@@ -1,5 +1,7 @@
 use std::sync::mpsc::Sender;
 
+use glam::Vec3;
+
 use crate::ScreenChunk;
 
 pub struct ColorsView;
@@ -9,16 +11,15 @@ impl super::View for ColorsView {
         let mut sc = ScreenChunk {
             from: 0,
             data: vec![],
+            samples: None,
         };
         for index in 0..(width * height) {
             let y = index as f32 / width as f32;
             let x = index % width;
-            let red = (x as f32 / width as f32 * 255.) as u32;
-            let green = (y as f32 / height as f32 * 255.) as u32;
-            let blue = 0; //(x * y as u32) % 255;
+            let red = x as f32 / width as f32;
+            let green = y / height as f32;
 
-            // buffer[index as usize] = blue | (green << 8) | (red << 16);
-            sc.data.push(blue | (green << 8) | (red << 16));
+            sc.data.push(Vec3::new(red, green, 0.));
         }
 
         buffer.send(sc).unwrap();
@@ -6,8 +6,22 @@ use std::sync::mpsc::Sender;
 pub use colors::*;
 pub use ray_tracing::*;
 
-use crate::ScreenChunk;
+use crate::{
+    utils::{aov::Aov, camera::CameraBuilder},
+    ScreenChunk,
+};
 
 pub trait View {
     fn step(&mut self, tx: Sender<ScreenChunk>, width: u32, height: u32);
+
+    /// Camera that the window controls can move, if the view has one
+    fn camera_mut(&mut self) -> Option<&mut CameraBuilder> {
+        None
+    }
+
+    /// Renders `aov` instead of the color from the next step on, for views that support it
+    fn set_aov(&mut self, _aov: Option<Aov>) {}
+
+    /// Filters the noise away from the next step on, for views that support it
+    fn set_denoise(&mut self, _denoise: bool) {}
 }
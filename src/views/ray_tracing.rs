@@ -1,109 +1,287 @@
-use std::{
-    sync::{mpsc::Sender, Arc, Mutex},
-    usize,
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::Sender,
+    Arc, Mutex,
 };
 
 use glam::Vec3;
 
 use crate::{
     utils::{
-        camera::Camera,
-        colors::{vec3àto_color, BLUE, GREEN, RED, YELLOW},
-        materials::{lambertian::Lambertian, metal::Metal},
-        meshes::{sphere::Sphere, Mesh},
+        aov::{Aov, FirstHit},
+        camera::CameraBuilder,
+        colors,
+        denoise::{self, Guide},
         ray_tracing::RayTracing,
+        scene::Scene,
+        scene_file::{self, SceneDescription},
     },
     ScreenChunk,
 };
 
-const MESHES_COUNT: usize = 4;
 pub struct RayTracingView {
-    meshes: [Arc<dyn Mesh>; MESHES_COUNT],
+    scene: Arc<Scene>,
+    camera: CameraBuilder,
     samples: usize,
     max_depth: usize,
+    /// Sent instead of the color when set, as raw values
+    aov: Option<Aov>,
+    denoise: bool,
 }
 
 impl Default for RayTracingView {
     fn default() -> Self {
+        Self::new(scene_file::default_scene())
+    }
+}
+
+impl RayTracingView {
+    pub fn new(description: SceneDescription) -> Self {
+        let mut scene = description.scene;
+        scene.build();
+
         Self {
-            meshes: [
-                Arc::new(Sphere::new(
-                    Vec3::new(0., 0., -1.2),
-                    0.5,
-                    Lambertian::new(BLUE),
-                )),
-                Arc::new(Sphere::new(Vec3::new(1., 0., -1.), 0.5, Metal::new(YELLOW))),
-                Arc::new(Sphere::new(Vec3::new(-1., 0., -1.), 0.5, Metal::new(RED))),
-                Arc::new(Sphere::new(
-                    Vec3::new(0., -100.5, -1.),
-                    100.,
-                    Lambertian::new(GREEN),
-                )),
-            ],
-            samples: 5,
-            max_depth: 100,
+            scene: Arc::new(scene),
+            camera: description.camera,
+            samples: description.samples,
+            max_depth: description.max_depth,
+            aov: None,
+            denoise: description.denoise,
         }
     }
 }
 
-impl RayTracingView {}
-
 impl super::View for RayTracingView {
+    fn camera_mut(&mut self) -> Option<&mut CameraBuilder> {
+        Some(&mut self.camera)
+    }
+
+    fn set_aov(&mut self, aov: Option<Aov>) {
+        self.aov = aov;
+    }
+
+    fn set_denoise(&mut self, denoise: bool) {
+        self.denoise = denoise;
+    }
+
     fn step(&mut self, buffer: Sender<ScreenChunk>, width: u32, height: u32) {
-        let camera = Camera::new(Vec3::new(0., 0., 0.), width, height);
+        let camera = self.camera.build(width, height);
 
-        let samples = self.samples;
+        let aov = self.aov;
+        // A single pass is all the output variables that cannot be averaged get
+        let samples = match aov {
+            Some(aov) if !aov.is_averaged() => 1,
+            _ => self.samples,
+        };
         let max_depth = self.max_depth;
+        // The output variables guide the denoiser, they are never filtered themselves
+        let filter = self.denoise && aov.is_none();
 
         let threads = std::thread::available_parallelism()
             .expect("Windows macos and linux know the amount of threads")
             .get();
 
-        let rt = RayTracing::<MESHES_COUNT>::new(self.meshes.clone(), camera, samples, max_depth);
+        // Every pass traces a single sample per pixel
+        let rt = RayTracing::new(self.scene.clone(), camera, max_depth, 1);
+
+        /*
+         * Progressive rendering: passes of 1 sample per pixel are summed in
+         * the accumulation buffer, and after each row the running average is sent.
+         *
+         * The accumulation lives as long as this step, a camera or scene change
+         * calls step again and so starts from scratch.
+         *
+         * The denoiser needs the whole frame, so when it is on rows are only
+         * accumulated, and the filtered frame is sent after every pass.
+         * Rows then send empty chunks, which stop the pass as soon as the
+         * receiver is gone.
+         */
+        std::thread::spawn(move || {
+            let accumulation = (0..height)
+                .map(|_| Mutex::new(vec![Vec3::ZERO; width as usize]))
+                .collect::<Vec<_>>();
+            // Albedos and normals are summed like the colors, the depth is the one of the first pass
+            let guides = (0..height)
+                .map(|_| Mutex::new(vec![Guide::from(FirstHit::BACKGROUND); width as usize]))
+                .collect::<Vec<_>>();
+
+            // If there is no receiver, every thread can be stopped
+            let stop = AtomicBool::new(false);
+
+            for pass in 1..=samples {
+                // Some threads are faster, so they can do multiple rows
+                let rows = Mutex::new((0..height as usize).collect::<Vec<_>>());
+
+                std::thread::scope(|scope| {
+                    for _ in 0..threads {
+                        // Draw row by row to allow multithreading
+                        scope.spawn(|| loop {
+                            if stop.load(Ordering::Relaxed) {
+                                break;
+                            }
 
-        // Some threads are faster, so they can do multiple rows
-        let rows = Arc::new(Mutex::new(
-            (0..height as usize).into_iter().collect::<Vec<_>>(),
-        ));
+                            let Some(y) = rows.lock().unwrap().pop() else {
+                                break;
+                            };
 
-        for _ in 0..threads {
-            let should_stop = Arc::new(Mutex::new(false));
-            let rows = rows.clone();
-            let buffer = buffer.clone();
+                            let mut sums = accumulation[y].lock().unwrap();
+                            let mut guides = guides[y].lock().unwrap();
 
-            let rt = rt.clone();
+                            let mut sc = ScreenChunk {
+                                from: y * width as usize,
+                                data: Vec::with_capacity(width as usize),
+                                samples: Some(pass),
+                            };
+
+                            for (x, sum) in sums.iter_mut().enumerate() {
+                                // Colors are rendered from 0 to 255, output variables as they are
+                                *sum += match aov {
+                                    Some(aov) => aov.value(&rt.first_hit(x as f32, y as f32)),
+                                    None => colors::vec3_to_scalar(
+                                        &rt.compute_pixel(x as f32, y as f32),
+                                    ),
+                                };
+                                sc.data.push(*sum / pass as f32);
+
+                                if filter {
+                                    let hit = rt.first_hit(x as f32, y as f32);
+                                    let guide = &mut guides[x];
+                                    guide.albedo += hit.albedo;
+                                    guide.normal += hit.normal;
+                                    if pass == 1 {
+                                        guide.depth = hit.depth;
+                                    }
+                                }
+                            }
+
+                            // The denoised frame is sent whole, an empty chunk still finds out
+                            // whether it is wanted
+                            if filter {
+                                sc.data.clear();
+                                sc.samples = None;
+                            }
+                            if buffer.send(sc).is_err() {
+                                stop.store(true, Ordering::Relaxed);
+                            };
+                        });
+                    }
+                });
 
-            // Draw row by row to allow multithreading
-            std::thread::spawn(move || loop {
-                let stop = should_stop.clone();
-                if *stop.lock().unwrap() {
+                if stop.load(Ordering::Relaxed) {
                     break;
                 }
 
-                let y = {
-                    let mut rows = rows.lock().unwrap();
-                    if let Some(row) = rows.pop() {
-                        row
-                    } else {
+                if filter {
+                    let color = accumulation
+                        .iter()
+                        .flat_map(|row| {
+                            let row = row.lock().unwrap();
+                            row.iter().map(|sum| *sum / pass as f32).collect::<Vec<_>>()
+                        })
+                        .collect::<Vec<_>>();
+                    let guides = guides
+                        .iter()
+                        .flat_map(|row| {
+                            let row = row.lock().unwrap();
+                            row.iter()
+                                .map(|guide| Guide {
+                                    albedo: guide.albedo / pass as f32,
+                                    ..*guide
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                        .collect::<Vec<_>>();
+
+                    let chunk = ScreenChunk {
+                        from: 0,
+                        data: denoise::denoise(width as usize, height as usize, &color, &guides),
+                        samples: Some(pass),
+                    };
+                    if buffer.send(chunk).is_err() {
                         break;
                     }
-                };
+                }
+            }
+        });
+    }
+}
 
-                let mut sc = ScreenChunk {
-                    from: y * width as usize,
-                    data: vec![],
-                };
+#[cfg(test)]
+mod tests {
+    use crate::{
+        utils::{aov::Aov, scene_file::default_scene},
+        views::{RayTracingView, View},
+    };
 
-                for x in 0..width {
-                    let color = rt.compute_pixel(x as f32, y as f32);
-                    sc.data.push(vec3àto_color(&color))
-                }
+    #[test]
+    fn every_pass_refines_every_row() {
+        let mut scene = default_scene();
+        scene.samples = 3;
+        scene.max_depth = 2;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        RayTracingView::new(scene).step(tx, 4, 5);
+
+        let chunks = rx.into_iter().collect::<Vec<_>>();
+
+        assert_eq!(chunks.len(), 3 * 5);
+        for pass in 1..=3 {
+            let mut rows = chunks
+                .iter()
+                .filter(|c| c.samples == Some(pass))
+                .map(|c| c.from / 4)
+                .collect::<Vec<_>>();
+            rows.sort();
+            assert_eq!(rows, vec![0, 1, 2, 3, 4]);
+        }
+
+        // Passes are sent in order
+        assert!(chunks.windows(2).all(|w| w[0].samples <= w[1].samples));
+    }
+
+    #[test]
+    fn ids_are_not_averaged() {
+        let mut scene = default_scene();
+        scene.samples = 3;
+
+        let mut view = RayTracingView::new(scene);
+        for (aov, passes) in [(Aov::ObjectId, 1), (Aov::Normal, 3)] {
+            view.set_aov(Some(aov));
+            let (tx, rx) = std::sync::mpsc::channel();
+            view.step(tx, 4, 5);
+
+            let chunks = rx.into_iter().collect::<Vec<_>>();
+            assert_eq!(chunks.len(), passes * 5);
+            if aov == Aov::ObjectId {
+                assert!(chunks
+                    .iter()
+                    .flat_map(|c| &c.data)
+                    .all(|p| p.x.fract() == 0.));
+            }
+        }
+    }
+
+    #[test]
+    fn denoised_frames_are_sent_whole() {
+        let mut scene = default_scene();
+        scene.samples = 2;
+        scene.max_depth = 2;
+
+        let mut view = RayTracingView::new(scene);
+        view.set_denoise(true);
+        let (tx, rx) = std::sync::mpsc::channel();
+        view.step(tx, 4, 5);
+
+        let (frames, rows): (Vec<_>, Vec<_>) =
+            rx.into_iter().partition(|chunk| !chunk.data.is_empty());
+        assert_eq!(rows.len(), 2 * 5);
+        assert!(rows.iter().all(|row| row.samples.is_none()));
 
-                // If there is no receiver, the thread can be killed
-                if let Err(_) = buffer.send(sc) {
-                    *stop.clone().lock().unwrap() = true;
-                };
-            });
+        assert_eq!(frames.len(), 2);
+        for (pass, chunk) in frames.iter().enumerate() {
+            assert_eq!((chunk.from, chunk.data.len()), (0, 20));
+            assert_eq!(chunk.samples, Some(pass + 1));
+            assert!(chunk.data.iter().all(|p| p.is_finite()));
         }
     }
 }